@@ -2,9 +2,9 @@
 
 use {
     alpenglow_vote::{
-        instruction::{finalize, notarize, skip},
+        instruction::{finalize, notarize, notarize_fallback, skip},
         state::VoteState,
-        vote::{FinalizationVote, NotarizationVote, SkipVote},
+        vote::{FinalizationVote, NotarizationFallbackVote, NotarizationVote, SkipVote},
     },
     mollusk_svm::Mollusk,
     mollusk_svm_bencher::MolluskComputeUnitBencher,
@@ -83,6 +83,19 @@ fn main() {
                 ],
             )
         })
+        .bench({
+            let vote_address = Pubkey::new_unique();
+            let authority = Pubkey::new_unique();
+            let vote = NotarizationFallbackVote::new(vote_slot, bank_hash, vote_slot, bank_hash);
+            (
+                "notarize_fallback",
+                &notarize_fallback(vote_address, authority, &vote),
+                &[
+                    (vote_address, vote_account(&authority)),
+                    (authority, Account::default()),
+                ],
+            )
+        })
         .bench({
             let vote_address = Pubkey::new_unique();
             let authority = Pubkey::new_unique();
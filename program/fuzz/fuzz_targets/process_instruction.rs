@@ -0,0 +1,68 @@
+#![no_main]
+
+use {
+    alpenglow_vote::{
+        fuzz::fuzz_process_instruction,
+        solana_program::{account_info::AccountInfo, pubkey::Pubkey},
+        state::VoteState,
+    },
+    libfuzzer_sys::fuzz_target,
+};
+
+/// The most accounts passed to a single instruction, one more than any
+/// instruction expects so that extra accounts are exercised too
+const MAX_ACCOUNTS: u8 = 6;
+
+// The first byte of the input is the number of accounts, the next
+// `VoteState::size()` bytes are used as the vote account data and the
+// remainder as the instruction data. The vote account comes first and the
+// remaining accounts are signers
+fuzz_target!(|input: &[u8]| {
+    let Some((account_count, input)) = input.split_first() else {
+        return;
+    };
+    let account_count = usize::from(account_count % (MAX_ACCOUNTS + 1));
+    let split = input.len().min(VoteState::size());
+    let (account_data, instruction_data) = input.split_at(split);
+
+    let program_id = alpenglow_vote::id();
+    let system_program = Pubkey::default();
+    let keys: Vec<Pubkey> = (0..account_count).map(|_| Pubkey::new_unique()).collect();
+    let mut lamports = vec![1_000_000_000; account_count];
+    let mut data: Vec<Vec<u8>> = (0..account_count)
+        .map(|index| {
+            if index == 0 {
+                account_data.to_vec()
+            } else {
+                vec![]
+            }
+        })
+        .collect();
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_vote_account = index == 0;
+            AccountInfo::new(
+                key,
+                !is_vote_account,
+                is_vote_account,
+                lamports,
+                data,
+                if is_vote_account {
+                    &program_id
+                } else {
+                    &system_program
+                },
+                false,
+                0,
+            )
+        })
+        .collect();
+
+    if let Err(panic) = fuzz_process_instruction(instruction_data, &accounts) {
+        std::panic::resume_unwind(panic);
+    }
+});
@@ -6,17 +6,29 @@ use solana_program::clock::Clock;
 use solana_program::clock::Epoch;
 use solana_program::clock::Slot;
 use solana_program::epoch_schedule::EpochSchedule;
+use solana_program::msg;
+use solana_program::program::{invoke, invoke_signed};
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
-use spl_pod::bytemuck::pod_from_bytes_mut;
-use spl_pod::primitives::PodU64;
+use solana_program::system_instruction;
+use spl_pod::bytemuck::{pod_from_bytes, pod_from_bytes_mut};
+use spl_pod::optional_keys::OptionalNonZeroPubkey;
+use spl_pod::primitives::{PodBool, PodU16, PodU64};
 
 use crate::error::VoteError;
-use crate::instruction::AuthorityType;
-use crate::state::{PodEpoch, VoteState};
+use crate::governance;
+use crate::instruction::{
+    derive_commission_config, AuthorityType, UpdateConfigFields, UpdateConfigInstructionData,
+    COMMISSION_CONFIG_SEED,
+};
+use crate::state::{CommissionConfig, PodEpoch, VoteState};
 
 /// Authorized Signer for vote instructions
+///
+/// The epoch of a stored `next_authorized_voter` is always non-zero, as an
+/// all-zero `next_authorized_voter` is read back as `None`. The current
+/// `authorized_voter` may have epoch zero
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable, Default, PartialEq)]
 pub struct AuthorizedVoter {
@@ -106,6 +118,31 @@ impl EpochCredit {
     pub fn set_prev_credits(&mut self, prev_credits: u64) {
         self.prev_credits = PodU64::from(prev_credits);
     }
+
+    /// The change since `prior`, an earlier snapshot of the same account, or
+    /// `None` if nothing changed. Credits only grow, so the delta is unsigned.
+    /// Once the epoch rolls over, the credits of `prior` are carried into
+    /// `prev_credits`, so they are part of the baseline
+    pub fn diff(&self, prior: &EpochCredit) -> Option<CreditDiff> {
+        let baseline = if self.epoch() == prior.epoch() {
+            prior.credits()
+        } else {
+            prior.prev_credits().saturating_add(prior.credits())
+        };
+        (self != prior).then(|| CreditDiff {
+            epoch: self.epoch(),
+            credits_delta: self.credits().saturating_sub(baseline),
+        })
+    }
+}
+
+/// The change between two snapshots of an `EpochCredit`, see `EpochCredit::diff`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CreditDiff {
+    /// The epoch of the later snapshot
+    pub epoch: Epoch,
+    /// The credits gained since the earlier snapshot
+    pub credits_delta: u64,
 }
 
 /// Authorize the given pubkey to withdraw or sign votes. This may be called multiple times,
@@ -115,29 +152,51 @@ pub(crate) fn authorize(
     new_authority: &Pubkey,
     vote_authorize: AuthorityType,
     authority: &Pubkey,
+    force: bool,
     clock: &Clock,
 ) -> Result<(), ProgramError> {
     let mut buffer = vote_account.try_borrow_mut_data()?;
-    let vote_state = pod_from_bytes_mut::<VoteState>(&mut buffer)?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
+
+    vote_state.promote_next_authorized_voter(clock.epoch);
 
     match vote_authorize {
         AuthorityType::Voter => {
-            // Current authorized withdrawer or voter must match
+            // Current authorized withdrawer or the voter in effect must match
             if vote_state.authorized_withdrawer != *authority
-                && vote_state.authorized_voter.voter != *authority
+                && vote_state.signer_for_vote(clock) != Some(*authority)
             {
                 return Err(ProgramError::MissingRequiredSignature);
             }
+            // No one can sign for the default pubkey, so the account could never vote again
+            if *new_authority == Pubkey::default() {
+                return Err(VoteError::InvalidAuthority.into());
+            }
 
             let epoch_in_effect = clock
                 .leader_schedule_epoch
                 .checked_add(1)
                 .ok_or(ProgramError::InvalidInstructionData)?;
+            // A malformed clock must not schedule the new voter in the past
+            if epoch_in_effect <= clock.epoch {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            // Replacing a voter that has not taken effect yet must be explicit
+            if let Some(pending) = vote_state.next_authorized_voter {
+                if pending.epoch() > clock.epoch && pending.voter != *new_authority {
+                    if !force {
+                        return Err(VoteError::PendingVoterOverwrite.into());
+                    }
+                    msg!(
+                        "Overwriting pending authorized voter {} for epoch {}",
+                        pending.voter,
+                        pending.epoch()
+                    );
+                }
+            }
             // Overwrite the next authorized voter
-            vote_state.next_authorized_voter = Some(AuthorizedVoter {
-                epoch: PodU64::from(epoch_in_effect),
-                voter: *new_authority,
-            });
+            vote_state
+                .set_next_authorized_voter(AuthorizedVoter::new(epoch_in_effect, *new_authority))?;
         }
         AuthorityType::Withdrawer => {
             // Current authorized withdrawer must match
@@ -147,6 +206,7 @@ pub(crate) fn authorize(
             vote_state.authorized_withdrawer = *new_authority;
         }
     }
+    vote_state.check_invariants()?;
     Ok(())
 }
 
@@ -158,8 +218,13 @@ pub(crate) fn withdraw(
     rent_sysvar: &Rent,
     clock: &Clock,
 ) -> Result<(), ProgramError> {
-    let vote_state = vote_account.data.borrow();
-    let vote_state = bytemuck::from_bytes::<VoteState>(&vote_state);
+    if lamports == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Older versions are read without migrating, so that an account short of
+    // rent for the current layout can still be emptied
+    let vote_state = VoteState::deserialize_versioned(&vote_account.try_borrow_data()?)?;
 
     if vote_state.authorized_withdrawer != *withdraw_pubkey {
         return Err(ProgramError::MissingRequiredSignature);
@@ -171,25 +236,23 @@ pub(crate) fn withdraw(
         .ok_or(ProgramError::InsufficientFunds)?;
 
     if remaining_balance == 0 {
-        let last_epoch_with_credits = u64::from(vote_state.epoch_credits.epoch);
-        let current_epoch = clock.epoch;
-        // if current_epoch - last_epoch_with_credits < 2 then the validator has received credits
-        // either in the current epoch or the previous epoch. If it's >= 2 then it has been at least
-        // one full epoch since the validator has received credits.
-        let reject_active_vote_account_close =
-            current_epoch.saturating_sub(last_epoch_with_credits) < 2;
-
-        if reject_active_vote_account_close {
-            return Err(VoteError::ActiveVoteAccountClose.into());
-        } else {
-            // Deinitialize upon zero-balance
-            VoteState::set_vote_account_state(vote_account, &VoteState::default())?;
-        }
+        check_idle(&vote_state, clock)?;
+        // Deinitialize upon zero-balance
+        VoteState::clear_vote_account_state(vote_account)?;
     } else {
         let min_rent_exempt_balance = rent_sysvar.minimum_balance(vote_account.data_len());
         if remaining_balance < min_rent_exempt_balance {
             return Err(ProgramError::InsufficientFunds);
         }
+        // Allowed, but an active validator is unlikely to mean to strip its account
+        if leaves_active_account_at_rent_floor(
+            &vote_state,
+            remaining_balance,
+            min_rent_exempt_balance,
+            clock,
+        ) {
+            msg!("Warning: withdrawal leaves an active vote account at the rent exempt minimum");
+        }
     }
 
     let mut vote_account_lamports = vote_account.try_borrow_mut_lamports()?;
@@ -207,58 +270,1144 @@ pub(crate) fn withdraw(
     Ok(())
 }
 
+/// Zero the vote state of an idle account, keeping its lamports so that it can
+/// be initialized again
+pub(crate) fn deinitialize(
+    vote_account: &AccountInfo,
+    withdraw_pubkey: &Pubkey,
+    clock: &Clock,
+) -> Result<(), ProgramError> {
+    let vote_state = VoteState::deserialize_versioned(&vote_account.try_borrow_data()?)?;
+    if vote_state.authorized_withdrawer != *withdraw_pubkey {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    check_idle(&vote_state, clock)?;
+
+    VoteState::clear_vote_account_state(vote_account)
+}
+
+/// Reject closing or deinitializing an account that has earned credits in the
+/// current or previous epoch
+fn check_idle(vote_state: &VoteState, clock: &Clock) -> Result<(), VoteError> {
+    let last_epoch_with_credits = vote_state.last_credited_epoch();
+    let current_epoch = clock.epoch;
+    // if current_epoch - last_epoch_with_credits < 2 then the validator has received credits
+    // either in the current epoch or the previous epoch. If it's >= 2 then it has been at least
+    // one full epoch since the validator has received credits.
+    if current_epoch.saturating_sub(last_epoch_with_credits) < 2 {
+        return Err(VoteError::ActiveVoteAccountClose);
+    }
+    Ok(())
+}
+
+/// Whether a partial withdrawal leaving `remaining_balance` takes an account that
+/// earned credits in the current or previous epoch down to its rent exempt minimum
+fn leaves_active_account_at_rent_floor(
+    vote_state: &VoteState,
+    remaining_balance: u64,
+    min_rent_exempt_balance: u64,
+    clock: &Clock,
+) -> bool {
+    remaining_balance == min_rent_exempt_balance && check_idle(vote_state, clock).is_err()
+}
+
+/// Shrink an over-allocated vote account to `VoteState::size()`, moving the
+/// rent freed by the smaller size to `recipient`
+pub(crate) fn realloc(
+    vote_account: &AccountInfo,
+    recipient: &AccountInfo,
+    withdraw_pubkey: &Pubkey,
+    rent_sysvar: &Rent,
+) -> Result<(), ProgramError> {
+    if VoteState::from_account_info(vote_account)?.authorized_withdrawer != *withdraw_pubkey {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let size = VoteState::size();
+    let freed_lamports = rent_sysvar
+        .minimum_balance(vote_account.data_len())
+        .saturating_sub(rent_sysvar.minimum_balance(size));
+    let remaining_balance = vote_account
+        .try_lamports()?
+        .checked_sub(freed_lamports)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    if !rent_sysvar.is_exempt(remaining_balance, size) {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    vote_account.realloc(size, false)?;
+
+    let mut vote_account_lamports = vote_account.try_borrow_mut_lamports()?;
+    **vote_account_lamports = remaining_balance;
+
+    let mut recipient_lamports = recipient.try_borrow_mut_lamports()?;
+    **recipient_lamports = recipient_lamports
+        .checked_add(freed_lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    Ok(())
+}
+
 pub(crate) fn update_validator_identity(
     vote_account: &AccountInfo,
     new_node_pubkey: &Pubkey,
     withdraw_pubkey: &Pubkey,
 ) -> Result<(), ProgramError> {
     let mut buffer = vote_account.try_borrow_mut_data()?;
-    let vote_state = pod_from_bytes_mut::<VoteState>(&mut buffer)?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
 
     if vote_state.authorized_withdrawer != *withdraw_pubkey {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
     vote_state.node_pubkey = *new_node_pubkey;
+    vote_state.check_invariants()?;
     Ok(())
 }
 
+pub(crate) fn set_collector_authority(
+    vote_account: &AccountInfo,
+    collector_authority: &OptionalNonZeroPubkey,
+    withdraw_pubkey: &Pubkey,
+) -> Result<(), ProgramError> {
+    let mut buffer = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
+
+    if vote_state.authorized_withdrawer != *withdraw_pubkey {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    vote_state.collector_authority = *collector_authority;
+    vote_state.check_invariants()?;
+    Ok(())
+}
+
+pub(crate) fn set_paused(
+    vote_account: &AccountInfo,
+    paused: &PodBool,
+    withdraw_pubkey: &Pubkey,
+) -> Result<(), ProgramError> {
+    let mut buffer = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
+
+    if vote_state.authorized_withdrawer != *withdraw_pubkey {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    vote_state.paused = *paused;
+    vote_state.check_invariants()?;
+    Ok(())
+}
+
+pub(crate) fn assign_rank(
+    vote_account: &AccountInfo,
+    rank: &PodU16,
+    governance_pubkey: &Pubkey,
+    program_data: &AccountInfo,
+) -> Result<(), ProgramError> {
+    governance::check_authority(governance_pubkey, program_data)?;
+
+    let mut buffer = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
+
+    vote_state.rank_assigned = PodBool::from(true);
+    vote_state.rank = *rank;
+    vote_state.check_invariants()?;
+    Ok(())
+}
+
+/// Commission increases are always allowed, so the epoch schedule is only
+/// fetched through `epoch_schedule` for decreases
 pub(crate) fn update_commission(
     vote_account: &AccountInfo,
     commission: u8,
     withdraw_pubkey: &Pubkey,
-    epoch_schedule: &EpochSchedule,
+    epoch_schedule: impl FnOnce() -> Result<EpochSchedule, VoteError>,
+    max_commission: Option<u8>,
     clock: &Clock,
 ) -> Result<(), ProgramError> {
     let mut buffer = vote_account.try_borrow_mut_data()?;
-    let vote_state = pod_from_bytes_mut::<VoteState>(&mut buffer)?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
 
     if vote_state.authorized_withdrawer != *withdraw_pubkey {
         return Err(ProgramError::MissingRequiredSignature);
     }
+    check_commission_update(
+        vote_state,
+        commission,
+        max_commission,
+        clock.slot,
+        epoch_schedule,
+    )?;
+
+    vote_state.record_commission_change(commission);
+
+    vote_state.check_invariants()?;
+    Ok(())
+}
+
+/// Check an update of the commission of `vote_state` to `commission` at `slot`:
+/// it may be at most 100 percent and at most `max_commission`, and may only be
+/// lowered or kept in the first half of an epoch. The epoch schedule is only
+/// fetched for decreases
+pub(crate) fn check_commission_update(
+    vote_state: &VoteState,
+    commission: u8,
+    max_commission: Option<u8>,
+    slot: Slot,
+    epoch_schedule: impl FnOnce() -> Result<EpochSchedule, VoteError>,
+) -> Result<(), VoteError> {
+    if commission > 100 {
+        return Err(VoteError::InvalidCommission);
+    }
+    check_max_commission(commission, max_commission)?;
 
     let is_commission_increase = commission > vote_state.commission;
-    if !is_commission_increase && !is_commission_update_allowed(clock.slot, epoch_schedule) {
-        return Err(VoteError::CommissionUpdateTooLate.into());
+    if !is_commission_increase && !is_commission_update_allowed(slot, &epoch_schedule()?) {
+        return Err(VoteError::CommissionUpdateTooLate);
     }
+    Ok(())
+}
 
-    vote_state.commission = commission;
+/// Write the fields of `instruction_data` flagged in `fields`, checking each
+/// one before any is written. The new node identity, if any, must already be
+/// checked to have signed
+pub(crate) fn update_config(
+    vote_account: &AccountInfo,
+    fields: UpdateConfigFields,
+    instruction_data: &UpdateConfigInstructionData,
+    withdraw_pubkey: &Pubkey,
+    epoch_schedule: impl FnOnce() -> Result<EpochSchedule, VoteError>,
+    max_commission: Option<u8>,
+    clock: &Clock,
+) -> Result<(), ProgramError> {
+    let mut buffer = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut buffer)?;
 
+    if vote_state.authorized_withdrawer != *withdraw_pubkey {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if fields.contains(UpdateConfigFields::COMMISSION) {
+        check_commission_update(
+            vote_state,
+            instruction_data.commission,
+            max_commission,
+            clock.slot,
+            epoch_schedule,
+        )?;
+    }
+    // Curve arithmetic is not available to the SBF program, so the point is
+    // only validated when the processor runs natively
+    #[cfg(not(target_os = "solana"))]
+    if fields.contains(UpdateConfigFields::BLS_PUBKEY) {
+        crate::bls::from_affine(instruction_data.bls_pubkey.0)?;
+    }
+
+    if fields.contains(UpdateConfigFields::COMMISSION) {
+        vote_state.record_commission_change(instruction_data.commission);
+    }
+    if fields.contains(UpdateConfigFields::NODE_PUBKEY) {
+        vote_state.node_pubkey = instruction_data.node_pubkey;
+    }
+    if fields.contains(UpdateConfigFields::BLS_PUBKEY) {
+        vote_state.bls_pubkey = instruction_data.bls_pubkey;
+    }
+
+    vote_state.check_invariants()?;
+    Ok(())
+}
+
+/// The cluster maximum commission from the optional `CommissionConfig` account,
+/// or `None` if it was not passed or governance has not created it yet. Only
+/// `set_commission_config` can assign the derived address to this program
+pub(crate) fn max_commission(
+    config_account: Option<&AccountInfo>,
+) -> Result<Option<u8>, ProgramError> {
+    let Some(config_account) = config_account else {
+        return Ok(None);
+    };
+    if *config_account.key != derive_commission_config().0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if *config_account.owner != crate::id() {
+        return Ok(None);
+    }
+    let data = config_account.try_borrow_data()?;
+    let config = pod_from_bytes::<CommissionConfig>(&data)?;
+    Ok(Some(config.max_commission))
+}
+
+/// Write the cluster `CommissionConfig`, creating its account on first use
+pub(crate) fn set_commission_config<'a>(
+    config_account: &AccountInfo<'a>,
+    governance_pubkey: &Pubkey,
+    program_data: &AccountInfo,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    config: &CommissionConfig,
+    rent: &Rent,
+) -> Result<(), ProgramError> {
+    governance::check_authority(governance_pubkey, program_data)?;
+    let (config_pubkey, bump) = derive_commission_config();
+    if *config_account.key != config_pubkey {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if *config_account.owner != crate::id() {
+        // Fund, allocate and assign separately rather than `create_account`,
+        // which fails if lamports were already sent to the address
+        let space = std::mem::size_of::<CommissionConfig>();
+        let lamports = rent
+            .minimum_balance(space)
+            .saturating_sub(config_account.lamports());
+        if lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, config_account.key, lamports),
+                &[
+                    payer.clone(),
+                    config_account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+        let signer_seeds: &[&[u8]] = &[COMMISSION_CONFIG_SEED, &[bump]];
+        invoke_signed(
+            &system_instruction::allocate(config_account.key, space as u64),
+            &[config_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(config_account.key, &crate::id()),
+            &[config_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+    }
+
+    let mut data = config_account.try_borrow_mut_data()?;
+    *pod_from_bytes_mut::<CommissionConfig>(&mut data)? = *config;
     Ok(())
 }
 
+/// Reject `commission` if it is above `max_commission`
+pub(crate) fn check_max_commission(
+    commission: u8,
+    max_commission: Option<u8>,
+) -> Result<(), VoteError> {
+    match max_commission {
+        Some(max_commission) if commission > max_commission => {
+            Err(VoteError::CommissionExceedsMaximum)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Given the current slot and epoch schedule, return the earliest slot at or after
+/// `current_slot` at which a commission decrease is allowed. Past the midpoint of
+/// an epoch this is the first slot of the next epoch, where the window reopens
+pub fn next_commission_decrease_slot(current_slot: Slot, epoch_schedule: &EpochSchedule) -> Slot {
+    if is_commission_update_allowed(current_slot, epoch_schedule) {
+        current_slot
+    } else {
+        let next_epoch = epoch_schedule.get_epoch(current_slot).saturating_add(1);
+        epoch_schedule.get_first_slot_in_epoch(next_epoch)
+    }
+}
+
+/// The first slot at which a voter authorized for `next_voter_epoch` can sign votes
+pub fn voter_activation_slot(next_voter_epoch: Epoch, epoch_schedule: &EpochSchedule) -> Slot {
+    epoch_schedule.get_first_slot_in_epoch(next_voter_epoch)
+}
+
+/// How far `slot` is into its epoch, as `(relative_slot, slots_in_epoch)`
+pub fn epoch_progress(slot: Slot, epoch_schedule: &EpochSchedule) -> (u64, u64) {
+    let (epoch, relative_slot) = epoch_schedule.get_epoch_and_slot_index(slot);
+    (relative_slot, epoch_schedule.get_slots_in_epoch(epoch))
+}
+
 /// Given the current slot and epoch schedule, determine if a commission change
 /// is allowed
 fn is_commission_update_allowed(slot: Slot, epoch_schedule: &EpochSchedule) -> bool {
     // always allowed during warmup epochs
-    if let Some(relative_slot) = slot
-        .saturating_sub(epoch_schedule.first_normal_slot)
-        .checked_rem(epoch_schedule.slots_per_epoch)
-    {
-        // allowed up to the midpoint of the epoch
-        relative_slot.saturating_mul(2) <= epoch_schedule.slots_per_epoch
-    } else {
-        // no slots per epoch, just allow it, even though this should never happen
-        true
+    if slot < epoch_schedule.first_normal_slot {
+        return true;
+    }
+    // allowed up to the midpoint of the epoch. With no slots per epoch this
+    // always allows it, even though that should never happen
+    let (relative_slot, slots_in_epoch) = epoch_progress(slot, epoch_schedule);
+    relative_slot.saturating_mul(2) <= slots_in_epoch
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_bls_signatures::keypair::Keypair as BlsKeypair;
+    use solana_bls_signatures::{Pubkey as BlsPubkey, BLS_PUBLIC_KEY_AFFINE_SIZE};
+    use solana_program::clock::Clock;
+    use solana_program::epoch_schedule::EpochSchedule;
+    use solana_program::program_error::ProgramError;
+    use solana_program::pubkey::Pubkey;
+    use solana_program::rent::Rent;
+    use spl_pod::primitives::PodU16;
+
+    use crate::accounting::{
+        assign_rank, authorize, deinitialize, epoch_progress, is_commission_update_allowed,
+        leaves_active_account_at_rent_floor, max_commission, next_commission_decrease_slot,
+        set_commission_config, update_commission, update_config, voter_activation_slot, withdraw,
+        CreditDiff, EpochCredit,
+    };
+    use crate::error::VoteError;
+    use crate::instruction::{AuthorityType, UpdateConfigFields, UpdateConfigInstructionData};
+    use crate::state::{CommissionConfig, VoteState};
+    use crate::test_utils::TestAccount;
+
+    #[test]
+    fn test_epoch_credit_diff() {
+        let prior = EpochCredit::new(5, 150, 100);
+
+        assert_eq!(None, prior.diff(&prior));
+
+        // Credits earned within the same epoch
+        assert_eq!(
+            Some(CreditDiff {
+                epoch: 5,
+                credits_delta: 20,
+            }),
+            EpochCredit::new(5, 170, 100).diff(&prior)
+        );
+
+        // The epoch rolled over, carrying the credits into `prev_credits`
+        assert_eq!(
+            Some(CreditDiff {
+                epoch: 6,
+                credits_delta: 10,
+            }),
+            EpochCredit::new(6, 260, 250).diff(&prior)
+        );
+    }
+
+    #[test]
+    fn test_next_commission_decrease_slot_second_half() {
+        let epoch_schedule = EpochSchedule::default();
+        let epoch = 256;
+        let first_slot = epoch_schedule.get_first_slot_in_epoch(epoch);
+        let slots_per_epoch = epoch_schedule.get_slots_in_epoch(epoch);
+
+        for current_slot in [
+            first_slot + slots_per_epoch / 2 + 1,
+            first_slot + slots_per_epoch * 3 / 4,
+            epoch_schedule.get_last_slot_in_epoch(epoch),
+        ] {
+            assert!(!is_commission_update_allowed(current_slot, &epoch_schedule));
+
+            let next_slot = next_commission_decrease_slot(current_slot, &epoch_schedule);
+            assert_eq!(epoch + 1, epoch_schedule.get_epoch(next_slot));
+            assert_eq!(epoch_schedule.get_first_slot_in_epoch(epoch + 1), next_slot);
+            assert!(is_commission_update_allowed(next_slot, &epoch_schedule));
+        }
+    }
+
+    #[test]
+    fn test_next_commission_decrease_slot_first_half() {
+        let epoch_schedule = EpochSchedule::default();
+        let first_slot = epoch_schedule.get_first_slot_in_epoch(256);
+
+        assert_eq!(
+            first_slot + 10,
+            next_commission_decrease_slot(first_slot + 10, &epoch_schedule)
+        );
+    }
+
+    #[test]
+    fn test_withdraw_over_allocated_account() {
+        let rent = Rent::default();
+        let authorized_withdrawer = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            authorized_withdrawer,
+            0,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        account.data.extend_from_slice(&[0; 100]);
+        let reserve = rent.minimum_balance(account.data.len());
+        account.lamports = reserve + 1_000;
+        let vote_account = account.info();
+        let mut recipient_account = TestAccount::with_data(vec![]);
+        let recipient = recipient_account.info();
+        // Idle, so that it can also be emptied
+        let clock = Clock {
+            epoch: 2,
+            ..Clock::default()
+        };
+
+        withdraw(
+            &vote_account,
+            &recipient,
+            1_000,
+            &authorized_withdrawer,
+            &rent,
+            &clock,
+        )
+        .unwrap();
+        assert_eq!(reserve, vote_account.lamports());
+
+        // Emptying the account deinitializes the vote state prefix
+        withdraw(
+            &vote_account,
+            &recipient,
+            reserve,
+            &authorized_withdrawer,
+            &rent,
+            &clock,
+        )
+        .unwrap();
+        assert_eq!(reserve + 1_000, recipient.lamports());
+        assert!(!VoteState::is_initialized_data(
+            &vote_account.try_borrow_data().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_voter_activation_slot() {
+        let epoch_schedule = EpochSchedule::default();
+        let slots_per_epoch = epoch_schedule.slots_per_epoch;
+        let first_normal_slot = epoch_schedule.first_normal_slot;
+        let first_normal_epoch = epoch_schedule.first_normal_epoch;
+
+        assert_eq!(
+            first_normal_slot,
+            voter_activation_slot(first_normal_epoch, &epoch_schedule)
+        );
+        assert_eq!(
+            first_normal_slot + 3 * slots_per_epoch,
+            voter_activation_slot(first_normal_epoch + 3, &epoch_schedule)
+        );
+        for epoch in [first_normal_epoch + 1, 256, 1000] {
+            let slot = voter_activation_slot(epoch, &epoch_schedule);
+            assert_eq!((epoch, 0), epoch_schedule.get_epoch_and_slot_index(slot));
+        }
+
+        // Warmup epochs double in length, starting at `MINIMUM_SLOTS_PER_EPOCH`
+        let epoch_schedule = EpochSchedule::custom(8192, 8192, true);
+        assert_eq!(0, voter_activation_slot(0, &epoch_schedule));
+        assert_eq!(32, voter_activation_slot(1, &epoch_schedule));
+        assert_eq!(96, voter_activation_slot(2, &epoch_schedule));
+        assert_eq!(224, voter_activation_slot(3, &epoch_schedule));
+        for epoch in 0..epoch_schedule.first_normal_epoch {
+            let slot = voter_activation_slot(epoch, &epoch_schedule);
+            assert_eq!((epoch, 0), epoch_schedule.get_epoch_and_slot_index(slot));
+        }
+    }
+
+    #[test]
+    fn test_epoch_progress() {
+        let epoch_schedule = EpochSchedule::default();
+        let epoch = 256;
+        let first_slot = epoch_schedule.get_first_slot_in_epoch(epoch);
+        let slots_per_epoch = epoch_schedule.slots_per_epoch;
+
+        assert_eq!(
+            (0, slots_per_epoch),
+            epoch_progress(first_slot, &epoch_schedule)
+        );
+        assert_eq!(
+            (slots_per_epoch / 2, slots_per_epoch),
+            epoch_progress(first_slot + slots_per_epoch / 2, &epoch_schedule)
+        );
+        assert_eq!(
+            (slots_per_epoch - 1, slots_per_epoch),
+            epoch_progress(
+                epoch_schedule.get_last_slot_in_epoch(epoch),
+                &epoch_schedule
+            )
+        );
+
+        // Warmup epochs are shorter
+        let epoch_schedule = EpochSchedule::custom(8192, 8192, true);
+        assert!(epoch_schedule.first_normal_slot > 0);
+        let warmup_epoch = 3;
+        let first_slot = epoch_schedule.get_first_slot_in_epoch(warmup_epoch);
+        let slots_in_epoch = epoch_schedule.get_slots_in_epoch(warmup_epoch);
+        assert!(slots_in_epoch < epoch_schedule.slots_per_epoch);
+        assert_eq!(
+            (0, slots_in_epoch),
+            epoch_progress(first_slot, &epoch_schedule)
+        );
+        assert_eq!(
+            (slots_in_epoch / 2, slots_in_epoch),
+            epoch_progress(first_slot + slots_in_epoch / 2, &epoch_schedule)
+        );
+        assert_eq!(
+            (slots_in_epoch - 1, slots_in_epoch),
+            epoch_progress(
+                epoch_schedule.get_last_slot_in_epoch(warmup_epoch),
+                &epoch_schedule
+            )
+        );
+        // Commission updates are always allowed during warmup
+        assert!(is_commission_update_allowed(
+            epoch_schedule.get_last_slot_in_epoch(warmup_epoch),
+            &epoch_schedule
+        ));
+    }
+
+    #[test]
+    fn test_update_commission_without_epoch_schedule() {
+        let authorized_withdrawer = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            authorized_withdrawer,
+            10,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let clock = Clock::default();
+        let missing_epoch_schedule = || Err(VoteError::MissingEpochScheduleSysvar);
+
+        // Decreases need the epoch schedule
+        assert_eq!(
+            Err(VoteError::MissingEpochScheduleSysvar.into()),
+            update_commission(
+                &vote_account,
+                5,
+                &authorized_withdrawer,
+                missing_epoch_schedule,
+                None,
+                &clock,
+            )
+        );
+
+        // Increases do not
+        update_commission(
+            &vote_account,
+            20,
+            &authorized_withdrawer,
+            missing_epoch_schedule,
+            None,
+            &clock,
+        )
+        .unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        assert_eq!(20, VoteState::deserialize(&data).unwrap().commission());
+    }
+
+    #[test]
+    fn test_update_commission_max_commission() {
+        let authorized_withdrawer = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            authorized_withdrawer,
+            10,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+
+        let mut config = TestAccount::commission_config(10);
+        let max_commission = max_commission(Some(&config.info())).unwrap();
+        assert_eq!(Some(10), max_commission);
+        let clock = Clock::default();
+
+        assert_eq!(
+            Err(VoteError::CommissionExceedsMaximum.into()),
+            update_commission(
+                &vote_account,
+                20,
+                &authorized_withdrawer,
+                || Ok(EpochSchedule::default()),
+                max_commission,
+                &clock,
+            )
+        );
+        update_commission(
+            &vote_account,
+            5,
+            &authorized_withdrawer,
+            || Ok(EpochSchedule::default()),
+            max_commission,
+            &clock,
+        )
+        .unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        assert_eq!(5, VoteState::deserialize(&data).unwrap().commission());
+    }
+
+    #[test]
+    fn test_update_config_all_or_nothing() {
+        let authorized_withdrawer = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            authorized_withdrawer,
+            10,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let epoch_schedule = EpochSchedule::default();
+        let late_clock = Clock {
+            slot: epoch_schedule.get_last_slot_in_epoch(256),
+            ..Clock::default()
+        };
+        let instruction_data = UpdateConfigInstructionData {
+            fields: 0,
+            commission: 5,
+            node_pubkey: Pubkey::new_unique(),
+            bls_pubkey: BlsKeypair::new().public.into(),
+        };
+        let fields = UpdateConfigFields::all();
+
+        // A decrease too late in the epoch rejects the other fields as well
+        assert_eq!(
+            Err(VoteError::CommissionUpdateTooLate.into()),
+            update_config(
+                &vote_account,
+                fields,
+                &instruction_data,
+                &authorized_withdrawer,
+                || Ok(epoch_schedule.clone()),
+                None,
+                &late_clock,
+            )
+        );
+        assert_eq!(
+            vote_state,
+            *VoteState::deserialize(&vote_account.try_borrow_data().unwrap()).unwrap()
+        );
+
+        // Bytes that are not a curve point are rejected
+        assert_eq!(
+            Err(VoteError::InvalidBlsPubkey.into()),
+            update_config(
+                &vote_account,
+                UpdateConfigFields::BLS_PUBKEY,
+                &UpdateConfigInstructionData {
+                    bls_pubkey: BlsPubkey([0xff; BLS_PUBLIC_KEY_AFFINE_SIZE]),
+                    ..instruction_data
+                },
+                &authorized_withdrawer,
+                || Ok(epoch_schedule.clone()),
+                None,
+                &Clock::default(),
+            )
+        );
+        assert_eq!(
+            vote_state,
+            *VoteState::deserialize(&vote_account.try_borrow_data().unwrap()).unwrap()
+        );
+
+        // Only the withdraw authority may update the config
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            update_config(
+                &vote_account,
+                fields,
+                &instruction_data,
+                &Pubkey::new_unique(),
+                || Ok(epoch_schedule.clone()),
+                None,
+                &Clock::default(),
+            )
+        );
+
+        update_config(
+            &vote_account,
+            fields,
+            &instruction_data,
+            &authorized_withdrawer,
+            || Ok(epoch_schedule.clone()),
+            None,
+            &Clock::default(),
+        )
+        .unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        let updated = VoteState::deserialize(&data).unwrap();
+        assert_eq!(5, updated.commission());
+        assert_eq!(instruction_data.node_pubkey, *updated.node_pubkey());
+        assert_eq!(instruction_data.bls_pubkey, *updated.bls_pubkey());
+    }
+
+    #[test]
+    fn test_max_commission_config_account() {
+        assert_eq!(Ok(None), max_commission(None));
+
+        let mut config = TestAccount::commission_config(10);
+        assert_eq!(Ok(Some(10)), max_commission(Some(&config.info())));
+
+        let mut config = TestAccount::commission_config(10);
+        config.key = Pubkey::new_unique();
+        assert_eq!(
+            Err(ProgramError::InvalidArgument),
+            max_commission(Some(&config.info()))
+        );
+
+        // Only the program can create an account at the derived address, so a
+        // config not owned by it was never set
+        let mut config = TestAccount::commission_config(10);
+        config.owner = Pubkey::new_unique();
+        assert_eq!(Ok(None), max_commission(Some(&config.info())));
+    }
+
+    #[test]
+    fn test_set_commission_config() {
+        let governance_authority = Pubkey::new_unique();
+        let mut program_data = TestAccount::program_data(Some(governance_authority));
+        let program_data = program_data.info();
+        let mut payer = TestAccount::with_data(vec![]);
+        let payer = payer.info();
+        let mut system_program = TestAccount::with_data(vec![]);
+        let system_program = system_program.info();
+        let rent = Rent::default();
+
+        let mut config = TestAccount::commission_config(10);
+        let config_account = config.info();
+        let set = |config: CommissionConfig, governance_pubkey: &Pubkey| {
+            set_commission_config(
+                &config_account,
+                governance_pubkey,
+                &program_data,
+                &payer,
+                &system_program,
+                &config,
+                &rent,
+            )
+        };
+
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            set(
+                CommissionConfig { max_commission: 5 },
+                &Pubkey::new_unique()
+            )
+        );
+        set(
+            CommissionConfig { max_commission: 5 },
+            &governance_authority,
+        )
+        .unwrap();
+        assert_eq!(Ok(Some(5)), max_commission(Some(&config_account)));
+
+        let mut other = TestAccount::commission_config(10);
+        other.key = Pubkey::new_unique();
+        assert_eq!(
+            Err(ProgramError::InvalidArgument),
+            set_commission_config(
+                &other.info(),
+                &governance_authority,
+                &program_data,
+                &payer,
+                &system_program,
+                &CommissionConfig { max_commission: 5 },
+                &rent,
+            )
+        );
+    }
+
+    #[test]
+    fn test_deinitialize() {
+        let authorized_withdrawer = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            authorized_withdrawer,
+            0,
+            BlsPubkey::default(),
+        );
+        assert_eq!(0, vote_state.last_credited_epoch());
+        let mut account = TestAccount {
+            lamports: 42,
+            ..TestAccount::new(&vote_state)
+        };
+        let vote_account = account.info();
+
+        let clock = Clock {
+            epoch: 1,
+            ..Clock::default()
+        };
+        assert_eq!(
+            Err(VoteError::ActiveVoteAccountClose.into()),
+            deinitialize(&vote_account, &authorized_withdrawer, &clock)
+        );
+
+        let clock = Clock {
+            epoch: 2,
+            ..Clock::default()
+        };
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            deinitialize(&vote_account, &Pubkey::new_unique(), &clock)
+        );
+        deinitialize(&vote_account, &authorized_withdrawer, &clock).unwrap();
+        assert_eq!(42, vote_account.lamports());
+        assert!(!VoteState::is_initialized_data(
+            &vote_account.try_borrow_data().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_withdraw_to_rent_floor() {
+        let rent = Rent::default();
+        let reserve = VoteState::get_rent_exempt_reserve(&rent);
+        let authorized_withdrawer = Pubkey::new_unique();
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            authorized_withdrawer,
+            0,
+            BlsPubkey::default(),
+        );
+        vote_state.set_epoch_credits(EpochCredit::new(5, 100, 0));
+
+        let active_clock = Clock {
+            epoch: 6,
+            ..Clock::default()
+        };
+        let idle_clock = Clock {
+            epoch: 7,
+            ..Clock::default()
+        };
+        assert!(leaves_active_account_at_rent_floor(
+            &vote_state,
+            reserve,
+            reserve,
+            &active_clock
+        ));
+        assert!(!leaves_active_account_at_rent_floor(
+            &vote_state,
+            reserve + 1,
+            reserve,
+            &active_clock
+        ));
+        assert!(!leaves_active_account_at_rent_floor(
+            &vote_state,
+            reserve,
+            reserve,
+            &idle_clock
+        ));
+
+        // Partial withdrawals to the floor succeed for active and idle accounts alike
+        for clock in [active_clock, idle_clock] {
+            let mut account = TestAccount {
+                lamports: reserve + 1_000,
+                ..TestAccount::new(&vote_state)
+            };
+            let vote_account = account.info();
+            let mut recipient_account = TestAccount::with_data(vec![]);
+            let recipient = recipient_account.info();
+
+            withdraw(
+                &vote_account,
+                &recipient,
+                1_000,
+                &authorized_withdrawer,
+                &rent,
+                &clock,
+            )
+            .unwrap();
+            assert_eq!(reserve, vote_account.lamports());
+            assert_eq!(1_000, recipient.lamports());
+            assert!(VoteState::is_initialized_data(
+                &vote_account.try_borrow_data().unwrap()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_assign_rank() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert_eq!(None, vote_state.rank());
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let governance_authority = Pubkey::new_unique();
+        let mut program_data = TestAccount::program_data(Some(governance_authority));
+        let program_data = program_data.info();
+
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            assign_rank(
+                &vote_account,
+                &PodU16::from(3),
+                &Pubkey::new_unique(),
+                &program_data
+            )
+        );
+        // Rank 0 is a valid assignment
+        for rank in [0, 3] {
+            assign_rank(
+                &vote_account,
+                &PodU16::from(rank),
+                &governance_authority,
+                &program_data,
+            )
+            .unwrap();
+            let data = vote_account.try_borrow_data().unwrap();
+            assert_eq!(Some(rank), VoteState::deserialize(&data).unwrap().rank());
+        }
+    }
+
+    #[test]
+    fn test_authorize_voter_overwrite_pending() {
+        let authorized_voter = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            authorized_voter,
+            10,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let clock = Clock {
+            epoch: 10,
+            leader_schedule_epoch: 11,
+            ..Clock::default()
+        };
+        let first_voter = Pubkey::new_unique();
+        let second_voter = Pubkey::new_unique();
+        let next_voter = || {
+            let data = vote_account.try_borrow_data().unwrap();
+            *VoteState::deserialize(&data)
+                .unwrap()
+                .next_authorized_voter()
+                .unwrap()
+                .voter()
+        };
+
+        authorize(
+            &vote_account,
+            &first_voter,
+            AuthorityType::Voter,
+            &authorized_voter,
+            false,
+            &clock,
+        )
+        .unwrap();
+
+        // Re-authorizing the pending voter is not an overwrite
+        authorize(
+            &vote_account,
+            &first_voter,
+            AuthorityType::Voter,
+            &authorized_voter,
+            false,
+            &clock,
+        )
+        .unwrap();
+
+        // A different voter in the same epoch needs force
+        assert_eq!(
+            Err(VoteError::PendingVoterOverwrite.into()),
+            authorize(
+                &vote_account,
+                &second_voter,
+                AuthorityType::Voter,
+                &authorized_voter,
+                false,
+                &clock,
+            )
+        );
+        assert_eq!(first_voter, next_voter());
+
+        authorize(
+            &vote_account,
+            &second_voter,
+            AuthorityType::Voter,
+            &authorized_voter,
+            true,
+            &clock,
+        )
+        .unwrap();
+        assert_eq!(second_voter, next_voter());
+
+        // Once the next voter has taken effect it can be replaced without force,
+        // signed by that voter
+        let clock = Clock {
+            epoch: 12,
+            leader_schedule_epoch: 13,
+            ..Clock::default()
+        };
+        authorize(
+            &vote_account,
+            &first_voter,
+            AuthorityType::Voter,
+            &second_voter,
+            false,
+            &clock,
+        )
+        .unwrap();
+        assert_eq!(first_voter, next_voter());
+    }
+
+    #[test]
+    fn test_authorize_voter_rejects_past_epoch() {
+        let authorized_voter = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            authorized_voter,
+            10,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let new_voter = Pubkey::new_unique();
+
+        // Leader schedule epoch behind the current epoch
+        for leader_schedule_epoch in [8, 9] {
+            let clock = Clock {
+                epoch: 10,
+                leader_schedule_epoch,
+                ..Clock::default()
+            };
+            assert_eq!(
+                Err(ProgramError::InvalidInstructionData),
+                authorize(
+                    &vote_account,
+                    &new_voter,
+                    AuthorityType::Voter,
+                    &authorized_voter,
+                    false,
+                    &clock,
+                )
+            );
+        }
+
+        let clock = Clock {
+            epoch: 10,
+            leader_schedule_epoch: 10,
+            ..Clock::default()
+        };
+        assert_eq!(
+            Ok(()),
+            authorize(
+                &vote_account,
+                &new_voter,
+                AuthorityType::Voter,
+                &authorized_voter,
+                false,
+                &clock,
+            )
+        );
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(11, vote_state.next_authorized_voter().unwrap().epoch());
     }
 }
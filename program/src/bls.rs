@@ -0,0 +1,262 @@
+//! BLS aggregation helpers for building and verifying certificates
+
+use {
+    crate::error::VoteError,
+    bitvec::prelude::*,
+    solana_bls_signatures::{
+        Pubkey as BlsPubkey, PubkeyProjective, Signature as BLSSignature, SignatureProjective,
+        BLS_PUBLIC_KEY_AFFINE_SIZE,
+    },
+    solana_program::pubkey::Pubkey,
+    std::collections::HashMap,
+};
+
+/// Sort `validators`, as `(node pubkey, BLS pubkey, stake)`, into the
+/// canonical rank order every node agrees on: by descending stake, ties broken
+/// by ascending pubkey. Afterwards the index of a validator in `validators` is
+/// its rank, so their BLS pubkeys are the `pubkeys_by_rank` of
+/// `aggregate_pubkeys`. Returns the rank of each pubkey; validators past
+/// `u16::MAX` are left unranked
+pub fn assign_ranks(validators: &mut [(Pubkey, BlsPubkey, u64)]) -> HashMap<Pubkey, u16> {
+    validators.sort_unstable_by(|(pubkey_a, _, stake_a), (pubkey_b, _, stake_b)| {
+        stake_b.cmp(stake_a).then_with(|| pubkey_a.cmp(pubkey_b))
+    });
+    validators
+        .iter()
+        .zip(0..=u16::MAX)
+        .map(|((pubkey, _, _), rank)| (*pubkey, rank))
+        .collect()
+}
+
+/// Aggregate the BLS pubkeys of the ranks set in `bitmap`.
+/// `pubkeys_by_rank` maps each rank to its pubkey, a bit set for a rank
+/// without a pubkey is an error
+pub fn aggregate_pubkeys(
+    pubkeys_by_rank: &[BlsPubkey],
+    bitmap: &BitVec<u8, Lsb0>,
+) -> Result<BlsPubkey, VoteError> {
+    let pubkeys = bitmap
+        .iter_ones()
+        .map(|rank| pubkeys_by_rank.get(rank).ok_or(VoteError::RankOutOfRange))
+        .collect::<Result<Vec<_>, _>>()?;
+    PubkeyProjective::aggregate(pubkeys)
+        .map(BlsPubkey::from)
+        .map_err(|_| VoteError::InvalidBlsAggregation)
+}
+
+/// Reject a signer bitmap with a bit set at or beyond `validator_count`.
+/// Unset bits past the count are allowed, so fixed size bitmaps can be checked
+/// through `BitSlice::from_slice`
+pub fn check_bitmap_in_range(
+    bitmap: &BitSlice<u8, Lsb0>,
+    validator_count: usize,
+) -> Result<(), VoteError> {
+    if bitmap.get(validator_count..).is_some_and(BitSlice::any) {
+        return Err(VoteError::BitmapOutOfRange);
+    }
+    Ok(())
+}
+
+/// Construct a BLS pubkey from its affine bytes, checking that they encode a
+/// valid curve point
+pub fn from_affine(affine: [u8; BLS_PUBLIC_KEY_AFFINE_SIZE]) -> Result<BlsPubkey, VoteError> {
+    let pubkey = BlsPubkey(affine);
+    PubkeyProjective::try_from(&pubkey).map_err(|_| VoteError::InvalidBlsPubkey)?;
+    Ok(pubkey)
+}
+
+/// Aggregate vote signatures into a certificate signature
+pub fn aggregate_signatures(sigs: &[BLSSignature]) -> Result<BLSSignature, VoteError> {
+    SignatureProjective::aggregate(sigs)
+        .map(BLSSignature::from)
+        .map_err(|_| VoteError::InvalidBlsAggregation)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::*;
+    use solana_bls_signatures::{
+        keypair::Keypair as BlsKeypair, Pubkey as BlsPubkey, Signature as BLSSignature,
+        VerifiablePubkey, BLS_PUBLIC_KEY_AFFINE_SIZE,
+    };
+    use solana_program::pubkey::Pubkey;
+
+    use crate::bls::{
+        aggregate_pubkeys, aggregate_signatures, assign_ranks, check_bitmap_in_range, from_affine,
+    };
+    use crate::error::VoteError;
+    use crate::state::VoteState;
+
+    fn pubkeys(count: usize) -> Vec<BlsPubkey> {
+        (0..count)
+            .map(|_| BlsKeypair::new().public.into())
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_pubkeys() {
+        let pubkeys_by_rank = pubkeys(4);
+
+        let two = bitvec![u8, Lsb0; 1, 0, 1, 0];
+        let reversed = [pubkeys_by_rank[2], pubkeys_by_rank[0]];
+        assert_eq!(
+            aggregate_pubkeys(&reversed, &bitvec![u8, Lsb0; 1, 1]).unwrap(),
+            aggregate_pubkeys(&pubkeys_by_rank, &two).unwrap()
+        );
+
+        let three = bitvec![u8, Lsb0; 0, 1, 1, 1];
+        let reversed = [pubkeys_by_rank[3], pubkeys_by_rank[2], pubkeys_by_rank[1]];
+        let aggregate = aggregate_pubkeys(&pubkeys_by_rank, &three).unwrap();
+        assert_eq!(
+            aggregate_pubkeys(&reversed, &bitvec![u8, Lsb0; 1, 1, 1]).unwrap(),
+            aggregate
+        );
+        assert_eq!(
+            aggregate,
+            aggregate_pubkeys(&pubkeys_by_rank, &three).unwrap()
+        );
+        assert_ne!(
+            aggregate,
+            aggregate_pubkeys(&pubkeys_by_rank, &two).unwrap()
+        );
+
+        // A single pubkey aggregates to itself
+        assert_eq!(
+            pubkeys_by_rank[1],
+            aggregate_pubkeys(&pubkeys_by_rank, &bitvec![u8, Lsb0; 0, 1]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_check_bitmap_in_range() {
+        let in_range = bitvec![u8, Lsb0; 1, 0, 1, 0, 0, 0, 0, 0];
+        assert_eq!(Ok(()), check_bitmap_in_range(&in_range, 3));
+        assert_eq!(Ok(()), check_bitmap_in_range(&in_range, 8));
+        assert_eq!(Ok(()), check_bitmap_in_range(&in_range, 100));
+        assert_eq!(
+            Err(VoteError::BitmapOutOfRange),
+            check_bitmap_in_range(&in_range, 2)
+        );
+
+        // A fixed size bitmap with a high bit set beyond the validator count
+        let mut fixed = [0u8; 512];
+        fixed[0] = 0b11;
+        assert_eq!(
+            Ok(()),
+            check_bitmap_in_range(BitSlice::from_slice(&fixed), 100)
+        );
+        fixed[511] = 0b1000_0000;
+        assert_eq!(
+            Err(VoteError::BitmapOutOfRange),
+            check_bitmap_in_range(BitSlice::from_slice(&fixed), 100)
+        );
+        assert_eq!(
+            Ok(()),
+            check_bitmap_in_range(BitSlice::from_slice(&fixed), 4096)
+        );
+    }
+
+    #[test]
+    fn test_affine_round_trip() {
+        let bls_pubkey: BlsPubkey = BlsKeypair::new().public.into();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            bls_pubkey,
+        );
+
+        let affine = vote_state.bls_pubkey_affine();
+        assert_eq!(Ok(bls_pubkey), from_affine(affine));
+
+        assert_eq!(
+            Err(VoteError::InvalidBlsPubkey),
+            from_affine([0xff; BLS_PUBLIC_KEY_AFFINE_SIZE])
+        );
+    }
+
+    #[test]
+    fn test_aggregate_signatures() {
+        let message = b"vote";
+        let keypairs: Vec<BlsKeypair> = (0..3).map(|_| BlsKeypair::new()).collect();
+        let pubkeys_by_rank: Vec<BlsPubkey> = keypairs
+            .iter()
+            .map(|keypair| keypair.public.into())
+            .collect();
+        let signatures: Vec<BLSSignature> = keypairs
+            .iter()
+            .map(|keypair| keypair.sign(message).into())
+            .collect();
+
+        for (count, bitmap) in [
+            (2, bitvec![u8, Lsb0; 1, 1, 0]),
+            (3, bitvec![u8, Lsb0; 1, 1, 1]),
+        ] {
+            let signature = aggregate_signatures(&signatures[..count]).unwrap();
+            let pubkey = aggregate_pubkeys(&pubkeys_by_rank, &bitmap).unwrap();
+            assert!(pubkey.verify_signature(&signature, message).unwrap());
+            assert!(!pubkey.verify_signature(&signature, b"other").unwrap());
+        }
+
+        assert_eq!(
+            Err(VoteError::InvalidBlsAggregation),
+            aggregate_signatures(&[])
+        );
+    }
+
+    #[test]
+    fn test_aggregate_pubkeys_out_of_range() {
+        let pubkeys_by_rank = pubkeys(2);
+        assert_eq!(
+            Err(VoteError::RankOutOfRange),
+            aggregate_pubkeys(&pubkeys_by_rank, &bitvec![u8, Lsb0; 1, 0, 1])
+        );
+        assert_eq!(
+            Err(VoteError::InvalidBlsAggregation),
+            aggregate_pubkeys(&pubkeys_by_rank, &bitvec![u8, Lsb0; 0, 0])
+        );
+    }
+
+    #[test]
+    fn test_assign_ranks() {
+        let bls_pubkeys = pubkeys(4);
+        let low = Pubkey::new_from_array([1; 32]);
+        let mid = Pubkey::new_from_array([2; 32]);
+        let high = Pubkey::new_from_array([3; 32]);
+        let whale = Pubkey::new_from_array([0; 32]);
+        let validators = [
+            (high, bls_pubkeys[0], 10),
+            (whale, bls_pubkeys[1], 100),
+            (low, bls_pubkeys[2], 10),
+            (mid, bls_pubkeys[3], 10),
+        ];
+
+        // Every input order yields the same ranks and rank order
+        let mut expected = validators;
+        let ranks = assign_ranks(&mut expected);
+        assert_eq!(
+            [
+                (whale, bls_pubkeys[1], 100),
+                (low, bls_pubkeys[2], 10),
+                (mid, bls_pubkeys[3], 10),
+                (high, bls_pubkeys[0], 10),
+            ],
+            expected
+        );
+        for (rank, (pubkey, _, _)) in expected.iter().enumerate() {
+            assert_eq!(Some(&(rank as u16)), ranks.get(pubkey));
+        }
+        for rotation in 1..validators.len() {
+            let mut rotated = validators;
+            rotated.rotate_left(rotation);
+            assert_eq!(ranks, assign_ranks(&mut rotated));
+            assert_eq!(expected, rotated);
+
+            rotated.reverse();
+            assert_eq!(ranks, assign_ranks(&mut rotated));
+            assert_eq!(expected, rotated);
+        }
+    }
+}
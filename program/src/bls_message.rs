@@ -2,6 +2,14 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(not(target_os = "solana"))]
+use {
+    crate::{
+        bls::{aggregate_pubkeys, check_bitmap_in_range},
+        error::VoteError,
+    },
+    solana_bls_signatures::{Pubkey as BlsPubkey, VerifiablePubkey},
+};
 use {
     crate::{certificate::Certificate, vote::Vote},
     bitvec::prelude::*,
@@ -35,6 +43,35 @@ pub struct CertificateMessage {
     pub bitmap: BitVec<u8, Lsb0>,
 }
 
+#[cfg(not(target_os = "solana"))]
+impl CertificateMessage {
+    /// Verify the aggregate signature over the signing bytes of the certified
+    /// vote, built from the certificate type, slot, block id and bank hash.
+    /// The bitmap is authoritative: the pubkeys are aggregated strictly from
+    /// the ranks it sets, looked up in `pubkeys_by_rank`, so a signature made
+    /// by any other set of ranks fails. A bit set beyond the ranks in
+    /// `pubkeys_by_rank` is `BitmapOutOfRange`
+    pub fn verify(&self, pubkeys_by_rank: &[BlsPubkey]) -> Result<(), VoteError> {
+        let message = self.certificate.vote()?.signing_bytes();
+        check_bitmap_in_range(&self.bitmap, pubkeys_by_rank.len())?;
+        let pubkey = aggregate_pubkeys(pubkeys_by_rank, &self.bitmap)?;
+        match pubkey.verify_signature(&self.signature, &message) {
+            Ok(true) => Ok(()),
+            Ok(false) | Err(_) => Err(VoteError::InvalidCertificateSignature),
+        }
+    }
+}
+
+impl CertificateMessage {
+    /// The length of this certificate serialized as a `BLSMessage`, for
+    /// estimating its transaction size. It grows with the length of the
+    /// bitmap
+    #[cfg(feature = "serde")]
+    pub fn serialized_len(&self) -> Result<usize, bincode::Error> {
+        bincode::serialized_size(&BLSMessage::Certificate(self.clone())).map(|len| len as usize)
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 #[allow(clippy::large_enum_variant)]
@@ -80,4 +117,122 @@ impl BLSMessage {
     pub fn serialize(&self) -> Vec<u8> {
         bincode::serialize(self).unwrap()
     }
+
+    #[cfg(feature = "serde")]
+    /// Serialize a BLS message as a frame, prefixed with its length as a
+    /// little endian `u32`, see `decode_stream`
+    pub fn serialize_frame(&self) -> Vec<u8> {
+        let message = self.serialize();
+        let mut frame = (message.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&message);
+        frame
+    }
+
+    #[cfg(feature = "serde")]
+    /// Decode a buffer of concatenated length prefixed BLS messages, returning
+    /// each message with the number of bytes its frame consumed. Errors if the
+    /// trailing frame is incomplete
+    pub fn decode_stream(buf: &[u8]) -> Result<Vec<(BLSMessage, usize)>, bincode::Error> {
+        const LENGTH_SIZE: usize = std::mem::size_of::<u32>();
+        let truncated = || {
+            Box::new(bincode::ErrorKind::Io(
+                std::io::ErrorKind::UnexpectedEof.into(),
+            ))
+        };
+
+        let mut messages = vec![];
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            let (length, rest) = remaining
+                .split_first_chunk::<LENGTH_SIZE>()
+                .ok_or_else(truncated)?;
+            let length = u32::from_le_bytes(*length) as usize;
+            let message = rest.get(..length).ok_or_else(truncated)?;
+            messages.push((
+                bincode::deserialize(message)?,
+                LENGTH_SIZE.saturating_add(length),
+            ));
+            remaining = &rest[length..];
+        }
+        Ok(messages)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use bitvec::prelude::*;
+    use solana_bls_signatures::Signature as BLSSignature;
+    use solana_program::hash::Hash;
+
+    use crate::bls_message::BLSMessage;
+    use crate::certificate::{Certificate, CertificateType};
+    use crate::vote::Vote;
+
+    fn messages() -> [BLSMessage; 2] {
+        [
+            BLSMessage::new_vote(Vote::new_skip_vote(42), BLSSignature::default(), 7),
+            BLSMessage::new_certificate(
+                Certificate {
+                    certificate_type: CertificateType::Notarize,
+                    slot: 42,
+                    block_id: Some(Hash::new_unique()),
+                    replayed_bank_hash: Some(Hash::new_unique()),
+                },
+                bitvec![u8, Lsb0; 1, 0, 1],
+                BLSSignature::default(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_decode_stream() {
+        let [vote, certificate] = messages();
+        let vote_frame = vote.serialize_frame();
+        let certificate_frame = certificate.serialize_frame();
+        let buf = [vote_frame.clone(), certificate_frame.clone()].concat();
+
+        assert_eq!(
+            vec![
+                (vote, vote_frame.len()),
+                (certificate, certificate_frame.len())
+            ],
+            BLSMessage::decode_stream(&buf).unwrap()
+        );
+        assert!(BLSMessage::decode_stream(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_certificate_serialized_len() {
+        let [_, BLSMessage::Certificate(mut certificate)] = messages() else {
+            unreachable!()
+        };
+        let short_len = certificate.serialized_len().unwrap();
+        assert_eq!(
+            BLSMessage::Certificate(certificate.clone())
+                .serialize()
+                .len(),
+            short_len
+        );
+
+        // A longer bitmap takes more space
+        certificate.bitmap.resize(4096, true);
+        assert_eq!(
+            BLSMessage::Certificate(certificate.clone())
+                .serialize()
+                .len(),
+            certificate.serialized_len().unwrap()
+        );
+        assert!(certificate.serialized_len().unwrap() > short_len);
+    }
+
+    #[test]
+    fn test_decode_stream_truncated() {
+        let [vote, certificate] = messages();
+        let buf = [vote.serialize_frame(), certificate.serialize_frame()].concat();
+
+        // Truncated trailing message and truncated trailing length prefix
+        for len in [buf.len() - 1, vote.serialize_frame().len() + 2] {
+            assert!(BLSMessage::decode_stream(&buf[..len]).is_err());
+        }
+    }
 }
@@ -2,7 +2,20 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use {solana_hash::Hash, solana_program::clock::Slot};
+#[cfg(not(target_os = "solana"))]
+use {
+    crate::{
+        bls::aggregate_signatures,
+        bls_message::{CertificateMessage, VoteMessage},
+    },
+    bitvec::prelude::*,
+    solana_bls_signatures::Signature as BLSSignature,
+};
+use {
+    crate::{error::VoteError, vote::Vote},
+    solana_hash::Hash,
+    solana_program::clock::Slot,
+};
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -20,6 +33,30 @@ pub enum CertificateType {
     Skip,
 }
 
+impl CertificateType {
+    /// The fraction of total stake required to produce this certificate, as
+    /// `(numerator, denominator)`
+    pub fn threshold_ratio(&self) -> (u64, u64) {
+        match self {
+            Self::FinalizeFast => (4, 5),
+            Self::Finalize | Self::Notarize | Self::NotarizeFallback | Self::Skip => (3, 5),
+        }
+    }
+
+    /// The percentage of total stake required to produce this certificate
+    pub fn threshold_percent(&self) -> u64 {
+        let (numerator, denominator) = self.threshold_ratio();
+        numerator * 100 / denominator
+    }
+
+    /// Whether `signed_stake` out of `total_stake` is enough to produce this certificate
+    pub fn meets_threshold(&self, signed_stake: u64, total_stake: u64) -> bool {
+        let (numerator, denominator) = self.threshold_ratio();
+        u128::from(signed_stake).saturating_mul(u128::from(denominator))
+            >= u128::from(total_stake).saturating_mul(u128::from(numerator))
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq)]
 /// Certificate Type in Alpenglow
@@ -33,3 +70,465 @@ pub struct Certificate {
     /// The bank hash of the block
     pub replayed_bank_hash: Option<Hash>,
 }
+
+impl Certificate {
+    /// Check that the block id and bank hash are present exactly when the
+    /// certificate type is built from notarization votes
+    pub fn validate_shape(&self) -> Result<(), VoteError> {
+        let is_notarization = match self.certificate_type {
+            CertificateType::Notarize
+            | CertificateType::NotarizeFallback
+            | CertificateType::FinalizeFast => true,
+            CertificateType::Finalize | CertificateType::Skip => false,
+        };
+        if self.block_id.is_some() != is_notarization
+            || self.replayed_bank_hash.is_some() != is_notarization
+        {
+            return Err(VoteError::MalformedCertificate);
+        }
+        Ok(())
+    }
+
+    /// The vote whose signing bytes the certificate signature covers: a
+    /// notarization vote for `Notarize` and `FinalizeFast`, and the vote named
+    /// by the type otherwise. Fails with `MalformedCertificate` if the block id
+    /// and bank hash do not match the type
+    pub fn vote(&self) -> Result<Vote, VoteError> {
+        self.validate_shape()?;
+        let block_id = self.block_id.unwrap_or_default();
+        let replayed_bank_hash = self.replayed_bank_hash.unwrap_or_default();
+        Ok(match self.certificate_type {
+            CertificateType::Notarize | CertificateType::FinalizeFast => {
+                Vote::new_notarization_vote(self.slot, block_id, replayed_bank_hash)
+            }
+            CertificateType::NotarizeFallback => {
+                Vote::new_notarization_fallback_vote(self.slot, block_id, replayed_bank_hash)
+            }
+            CertificateType::Finalize => Vote::new_finalization_vote(self.slot),
+            CertificateType::Skip => Vote::new_skip_vote(self.slot),
+        })
+    }
+}
+
+/// Whether `a` and `b` certify incompatible outcomes for the same slot, which
+/// can only happen if validators equivocated: a skip certificate together
+/// with a notarize, fast finalize or finalize certificate, or notarizations of
+/// different blocks. Notarize fallback certificates conflict with neither, as
+/// a slot may be both skipped and fallback notarized
+pub fn certificates_conflict(a: &Certificate, b: &Certificate) -> bool {
+    if a.slot != b.slot {
+        return false;
+    }
+    let is_skip = |certificate: &Certificate| certificate.certificate_type == CertificateType::Skip;
+    let is_notarization = |certificate: &Certificate| {
+        matches!(
+            certificate.certificate_type,
+            CertificateType::Notarize | CertificateType::FinalizeFast
+        )
+    };
+    let conflicts_with_skip = |certificate: &Certificate| {
+        is_notarization(certificate) || certificate.certificate_type == CertificateType::Finalize
+    };
+    (is_skip(a) && conflicts_with_skip(b))
+        || (is_skip(b) && conflicts_with_skip(a))
+        || (is_notarization(a) && is_notarization(b) && a.block_id != b.block_id)
+}
+
+/// Accumulates `VoteMessage`s towards a `Certificate`, tracking the
+/// signed stake until the threshold for the certificate type is met
+#[cfg(not(target_os = "solana"))]
+#[derive(Clone, Debug)]
+pub struct CertificateBuilder {
+    certificate: Certificate,
+    signatures: Vec<BLSSignature>,
+    bitmap: BitVec<u8, Lsb0>,
+    signed_stake: u64,
+}
+
+#[cfg(not(target_os = "solana"))]
+impl CertificateBuilder {
+    /// Create a builder for `certificate`
+    pub fn new(certificate: Certificate) -> Self {
+        Self {
+            certificate,
+            signatures: Vec::new(),
+            bitmap: BitVec::new(),
+            signed_stake: 0,
+        }
+    }
+
+    /// Add a vote with the stake of the voting rank.
+    /// The caller is responsible for only adding votes that belong to this
+    /// certificate, votes from a rank that was already added are ignored
+    pub fn add_vote(&mut self, msg: &VoteMessage, rank_stake: u64) {
+        let rank = usize::from(msg.rank);
+        if self.bitmap.len() <= rank {
+            self.bitmap.resize(rank.saturating_add(1), false);
+        }
+        if self.bitmap.replace(rank, true) {
+            return;
+        }
+        self.signatures.push(msg.signature);
+        self.signed_stake = self.signed_stake.saturating_add(rank_stake);
+    }
+
+    /// The stake of all the votes added so far
+    pub fn signed_stake(&self) -> u64 {
+        self.signed_stake
+    }
+
+    /// Produce the certificate message once the threshold for the certificate
+    /// type is met, `None` otherwise
+    pub fn finish(self, total_stake: u64) -> Option<CertificateMessage> {
+        if !self
+            .certificate
+            .certificate_type
+            .meets_threshold(self.signed_stake, total_stake)
+        {
+            return None;
+        }
+        let signature = aggregate_signatures(&self.signatures).ok()?;
+        Some(CertificateMessage {
+            certificate: self.certificate,
+            signature,
+            bitmap: self.bitmap,
+        })
+    }
+}
+
+/// The stake of every rank that signed any of `certs`, counting a rank that
+/// signed several of them once. Ranks missing from `stake_by_rank` have no stake
+#[cfg(not(target_os = "solana"))]
+pub fn combined_signed_stake(certs: &[&CertificateMessage], stake_by_rank: &[u64]) -> u64 {
+    let mut signers: BitVec<u8, Lsb0> = BitVec::new();
+    for cert in certs {
+        if signers.len() < cert.bitmap.len() {
+            signers.resize(cert.bitmap.len(), false);
+        }
+        signers[..cert.bitmap.len()] |= cert.bitmap.as_bitslice();
+    }
+    signers
+        .iter_ones()
+        .filter_map(|rank| stake_by_rank.get(rank))
+        .fold(0u64, |total, stake| total.saturating_add(*stake))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::prelude::*;
+    use solana_bls_signatures::keypair::Keypair as BlsKeypair;
+    use solana_bls_signatures::{Pubkey as BlsPubkey, Signature as BLSSignature};
+    use solana_hash::Hash;
+
+    use crate::bls::aggregate_signatures;
+    use crate::bls_message::{CertificateMessage, VoteMessage};
+    use crate::certificate::{
+        certificates_conflict, combined_signed_stake, Certificate, CertificateBuilder,
+        CertificateType,
+    };
+    use crate::error::VoteError;
+    use crate::vote::Vote;
+
+    #[test]
+    fn test_meets_threshold() {
+        assert!(!CertificateType::Notarize.meets_threshold(59, 100));
+        assert!(CertificateType::Notarize.meets_threshold(60, 100));
+        assert!(!CertificateType::FinalizeFast.meets_threshold(79, 100));
+        assert!(CertificateType::FinalizeFast.meets_threshold(80, 100));
+        assert!(CertificateType::Skip.meets_threshold(u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn test_threshold_ratio() {
+        let cases = [
+            (CertificateType::Finalize, (3, 5), 60),
+            (CertificateType::FinalizeFast, (4, 5), 80),
+            (CertificateType::Notarize, (3, 5), 60),
+            (CertificateType::NotarizeFallback, (3, 5), 60),
+            (CertificateType::Skip, (3, 5), 60),
+        ];
+        for (certificate_type, ratio, percent) in cases {
+            assert_eq!(certificate_type.threshold_ratio(), ratio);
+            assert_eq!(certificate_type.threshold_percent(), percent);
+
+            // Exactly at the ratio meets the threshold, one below does not
+            let (numerator, denominator) = ratio;
+            let total_stake = denominator * 1_000;
+            let boundary = numerator * 1_000;
+            assert!(certificate_type.meets_threshold(boundary, total_stake));
+            assert!(!certificate_type.meets_threshold(boundary - 1, total_stake));
+        }
+    }
+
+    #[test]
+    fn test_validate_shape() {
+        let notarize = Certificate {
+            certificate_type: CertificateType::Notarize,
+            slot: 42,
+            block_id: Some(Hash::new_unique()),
+            replayed_bank_hash: Some(Hash::new_unique()),
+        };
+        assert_eq!(Ok(()), notarize.validate_shape());
+
+        let missing_block_id = Certificate {
+            block_id: None,
+            ..notarize.clone()
+        };
+        assert_eq!(
+            Err(VoteError::MalformedCertificate),
+            missing_block_id.validate_shape()
+        );
+
+        let skip = Certificate {
+            certificate_type: CertificateType::Skip,
+            slot: 42,
+            block_id: None,
+            replayed_bank_hash: None,
+        };
+        assert_eq!(Ok(()), skip.validate_shape());
+
+        let skip_with_block_id = Certificate {
+            block_id: notarize.block_id,
+            ..skip
+        };
+        assert_eq!(
+            Err(VoteError::MalformedCertificate),
+            skip_with_block_id.validate_shape()
+        );
+    }
+
+    #[test]
+    fn test_certificates_conflict() {
+        let block_id = Some(Hash::new_unique());
+        let bank_hash = Some(Hash::new_unique());
+        let certificate = |certificate_type, slot, block_id, replayed_bank_hash| Certificate {
+            certificate_type,
+            slot,
+            block_id,
+            replayed_bank_hash,
+        };
+        let notarize = certificate(CertificateType::Notarize, 42, block_id, bank_hash);
+        let finalize_fast = certificate(CertificateType::FinalizeFast, 42, block_id, bank_hash);
+        let finalize = certificate(CertificateType::Finalize, 42, None, None);
+        let skip = certificate(CertificateType::Skip, 42, None, None);
+
+        // Notarization and skip of the same slot conflict, in either order
+        assert!(certificates_conflict(&notarize, &skip));
+        assert!(certificates_conflict(&skip, &notarize));
+        assert!(certificates_conflict(&finalize, &skip));
+        assert!(certificates_conflict(&skip, &finalize_fast));
+
+        // Certificates for the same block do not
+        assert!(!certificates_conflict(&notarize, &finalize_fast));
+        assert!(!certificates_conflict(&notarize, &finalize));
+        assert!(!certificates_conflict(&notarize, &notarize));
+        assert!(!certificates_conflict(&skip, &skip));
+
+        // Nor do certificates for different slots
+        let next_skip = certificate(CertificateType::Skip, 43, None, None);
+        assert!(!certificates_conflict(&notarize, &next_skip));
+
+        // Notarizations of different blocks conflict, fallbacks do not
+        let other_block = Some(Hash::new_unique());
+        let other_notarize = certificate(CertificateType::Notarize, 42, other_block, bank_hash);
+        assert!(certificates_conflict(&notarize, &other_notarize));
+        assert!(certificates_conflict(&finalize_fast, &other_notarize));
+        let fallback = certificate(CertificateType::NotarizeFallback, 42, block_id, bank_hash);
+        let other_fallback = certificate(
+            CertificateType::NotarizeFallback,
+            42,
+            other_block,
+            bank_hash,
+        );
+        assert!(!certificates_conflict(&fallback, &other_fallback));
+        assert!(!certificates_conflict(&other_notarize, &fallback));
+
+        // A slot may be both skipped and fallback notarized
+        assert!(!certificates_conflict(&skip, &fallback));
+        assert!(!certificates_conflict(&fallback, &skip));
+    }
+
+    #[test]
+    fn test_certificate_builder_crosses_threshold() {
+        let slot = 42;
+        let block_id = Hash::new_unique();
+        let bank_hash = Hash::new_unique();
+        let vote = Vote::new_notarization_vote(slot, block_id, bank_hash);
+        let certificate = Certificate {
+            certificate_type: CertificateType::Notarize,
+            slot,
+            block_id: Some(block_id),
+            replayed_bank_hash: Some(bank_hash),
+        };
+
+        let stake_per_rank = 100;
+        let total_stake = stake_per_rank * 5;
+        let mut builder = CertificateBuilder::new(certificate.clone());
+
+        for rank in 0..3u16 {
+            assert!(builder.clone().finish(total_stake).is_none());
+            let vote_message = VoteMessage {
+                vote,
+                signature: BlsKeypair::new().sign(b"vote").into(),
+                rank,
+            };
+            builder.add_vote(&vote_message, stake_per_rank);
+            // Adding the same rank twice does not double count
+            builder.add_vote(&vote_message, stake_per_rank);
+        }
+        assert_eq!(stake_per_rank * 3, builder.signed_stake());
+
+        let certificate_message = builder.finish(total_stake).unwrap();
+        assert_eq!(certificate, certificate_message.certificate);
+        assert_eq!(3, certificate_message.bitmap.count_ones());
+    }
+
+    #[test]
+    fn test_combined_signed_stake() {
+        let certificate_message = |certificate_type, bits: &[bool]| CertificateMessage {
+            certificate: Certificate {
+                certificate_type,
+                slot: 42,
+                block_id: Some(Hash::new_unique()),
+                replayed_bank_hash: Some(Hash::new_unique()),
+            },
+            signature: BLSSignature::default(),
+            bitmap: bits.iter().collect(),
+        };
+        let stake_by_rank = [1, 10, 100, 1_000, 10_000];
+
+        // Ranks 1 and 2 signed both certificates
+        let notarize = certificate_message(CertificateType::Notarize, &[true, true, true]);
+        let fallback = certificate_message(
+            CertificateType::NotarizeFallback,
+            &[false, true, true, false, true],
+        );
+
+        assert_eq!(111, combined_signed_stake(&[&notarize], &stake_by_rank));
+        assert_eq!(10_110, combined_signed_stake(&[&fallback], &stake_by_rank));
+        assert_eq!(
+            10_111,
+            combined_signed_stake(&[&notarize, &fallback], &stake_by_rank)
+        );
+        assert_eq!(
+            10_111,
+            combined_signed_stake(&[&fallback, &notarize, &fallback], &stake_by_rank)
+        );
+        assert_eq!(0, combined_signed_stake(&[], &stake_by_rank));
+
+        // Ranks without stake are ignored
+        assert_eq!(
+            11,
+            combined_signed_stake(&[&fallback, &notarize], &stake_by_rank[..2])
+        );
+    }
+
+    #[test]
+    fn test_certificate_vote() {
+        let block_id = Hash::new_unique();
+        let replayed_bank_hash = Hash::new_unique();
+        let certificate = |certificate_type, hashes: Option<(Hash, Hash)>| Certificate {
+            certificate_type,
+            slot: 42,
+            block_id: hashes.map(|(block_id, _)| block_id),
+            replayed_bank_hash: hashes.map(|(_, replayed_bank_hash)| replayed_bank_hash),
+        };
+        let hashes = Some((block_id, replayed_bank_hash));
+
+        for (certificate_type, vote) in [
+            (
+                CertificateType::Notarize,
+                Vote::new_notarization_vote(42, block_id, replayed_bank_hash),
+            ),
+            (
+                CertificateType::FinalizeFast,
+                Vote::new_notarization_vote(42, block_id, replayed_bank_hash),
+            ),
+            (
+                CertificateType::NotarizeFallback,
+                Vote::new_notarization_fallback_vote(42, block_id, replayed_bank_hash),
+            ),
+        ] {
+            assert_eq!(Ok(vote), certificate(certificate_type, hashes).vote());
+            assert_eq!(
+                Err(VoteError::MalformedCertificate),
+                certificate(certificate_type, None).vote()
+            );
+        }
+        for (certificate_type, vote) in [
+            (CertificateType::Finalize, Vote::new_finalization_vote(42)),
+            (CertificateType::Skip, Vote::new_skip_vote(42)),
+        ] {
+            assert_eq!(Ok(vote), certificate(certificate_type, None).vote());
+            assert_eq!(
+                Err(VoteError::MalformedCertificate),
+                certificate(certificate_type, hashes).vote()
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_bitmap_is_authoritative() {
+        let keypairs: Vec<BlsKeypair> = (0..4).map(|_| BlsKeypair::new()).collect();
+        let pubkeys_by_rank: Vec<BlsPubkey> = keypairs
+            .iter()
+            .map(|keypair| keypair.public.into())
+            .collect();
+        let signature_by = |ranks: &[usize], vote: Vote| {
+            let message = vote.signing_bytes();
+            let signatures: Vec<BLSSignature> = ranks
+                .iter()
+                .map(|rank| keypairs[*rank].sign(&message).into())
+                .collect();
+            aggregate_signatures(&signatures).unwrap()
+        };
+        let certificate_message = |signature| CertificateMessage {
+            certificate: Certificate {
+                certificate_type: CertificateType::Finalize,
+                slot: 42,
+                block_id: None,
+                replayed_bank_hash: None,
+            },
+            signature,
+            bitmap: bitvec![u8, Lsb0; 0, 1, 1],
+        };
+        let finalize = Vote::new_finalization_vote(42);
+
+        // The bitmap claims ranks 1 and 2
+        assert_eq!(
+            Ok(()),
+            certificate_message(signature_by(&[1, 2], finalize)).verify(&pubkeys_by_rank)
+        );
+
+        // A signature by ranks 1 and 3 does not verify against the claimed ranks
+        assert_eq!(
+            Err(VoteError::InvalidCertificateSignature),
+            certificate_message(signature_by(&[1, 3], finalize)).verify(&pubkeys_by_rank)
+        );
+
+        // Nor does a signature over a vote other than the certified one
+        for vote in [Vote::new_skip_vote(42), Vote::new_finalization_vote(43)] {
+            assert_eq!(
+                Err(VoteError::InvalidCertificateSignature),
+                certificate_message(signature_by(&[1, 2], vote)).verify(&pubkeys_by_rank)
+            );
+        }
+
+        // A malformed certificate has no message to verify
+        let mut malformed = certificate_message(signature_by(&[1, 2], finalize));
+        malformed.certificate.block_id = Some(Hash::new_unique());
+        assert_eq!(
+            Err(VoteError::MalformedCertificate),
+            malformed.verify(&pubkeys_by_rank)
+        );
+
+        // Ranks beyond the known validators are rejected before verification
+        let mut out_of_range = certificate_message(signature_by(&[1, 2], finalize));
+        out_of_range.bitmap.resize(8, false);
+        out_of_range.bitmap.set(7, true);
+        assert_eq!(
+            Err(VoteError::BitmapOutOfRange),
+            out_of_range.verify(&pubkeys_by_rank)
+        );
+    }
+}
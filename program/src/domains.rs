@@ -0,0 +1,19 @@
+//! Domain separation tags for signing votes
+//!
+//! `Vote::signing_bytes` prefixes the vote payload with the tag for its vote
+//! type, so a signature over one vote type can never be replayed as another
+
+/// Domain separation tag for notarization votes
+pub const NOTARIZE_DOMAIN: &[u8] = b"ALPENGLOW_NOTARIZE_V1";
+
+/// Domain separation tag for finalization votes
+pub const FINALIZE_DOMAIN: &[u8] = b"ALPENGLOW_FINALIZE_V1";
+
+/// Domain separation tag for skip votes
+pub const SKIP_DOMAIN: &[u8] = b"ALPENGLOW_SKIP_V1";
+
+/// Domain separation tag for notarization fallback votes
+pub const NOTARIZE_FALLBACK_DOMAIN: &[u8] = b"ALPENGLOW_NOTARIZE_FALLBACK_V1";
+
+/// Domain separation tag for skip fallback votes
+pub const SKIP_FALLBACK_DOMAIN: &[u8] = b"ALPENGLOW_SKIP_FALLBACK_V1";
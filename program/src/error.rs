@@ -1,59 +1,252 @@
 //! Error types
 
+#[cfg(not(any(feature = "derive-errors", feature = "minimal-errors")))]
+compile_error!("either the `derive-errors` (default) or `minimal-errors` feature must be enabled");
+
+#[cfg(not(feature = "minimal-errors"))]
+use thiserror::Error;
 use {
     num_derive::FromPrimitive,
     solana_program::{decode_error::DecodeError, program_error::ProgramError},
-    thiserror::Error,
 };
 
 /// Errors that may be returned by the program.
-#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+#[cfg_attr(not(feature = "minimal-errors"), derive(Error))]
+#[derive(Clone, Copy, Debug, Eq, FromPrimitive, PartialEq)]
 pub enum VoteError {
     /// Closing an active vote account
-    #[error("Cannot close vote account unless it stopped voting at least one full epoch ago")]
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Cannot close vote account unless it stopped voting at least one full epoch ago")
+    )]
     ActiveVoteAccountClose,
 
     /// Increasing commission too late into the epoch
-    #[error("Cannot update commission at this point in the epoch")]
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Cannot update commission at this point in the epoch")
+    )]
     CommissionUpdateTooLate,
 
     /// Invalid instruction
-    #[error("Invalid instruction")]
+    #[cfg_attr(not(feature = "minimal-errors"), error("Invalid instruction"))]
     InvalidInstruction,
 
     /// Invalid Vote Authorize enum
-    #[error("Invalid vote authorize")]
+    #[cfg_attr(not(feature = "minimal-errors"), error("Invalid vote authorize"))]
     InvalidAuthorizeType,
 
     /// Missing epoch schedule sysvar
-    #[error("Missing epoch schedule sysvar")]
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Missing epoch schedule sysvar")
+    )]
     MissingEpochScheduleSysvar,
 
     /// Missing slot hashes sysvar
-    #[error("Missing slot hashes sysvar")]
+    #[cfg_attr(not(feature = "minimal-errors"), error("Missing slot hashes sysvar"))]
     MissingSlotHashesSysvar,
 
     /// Replay bank hash mismatch
-    #[error("Replay bank hash mismatch")]
+    #[cfg_attr(not(feature = "minimal-errors"), error("Replay bank hash mismatch"))]
     ReplayBankHashMismatch,
 
     /// Skip slot is present on this fork
-    #[error("Skipped slot is present on this fork")]
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Skipped slot is present on this fork")
+    )]
     SkipSlotPresent,
 
     /// Skip slot exceeds clock slot
-    #[error("Skip slot exceeds clock slot")]
+    #[cfg_attr(not(feature = "minimal-errors"), error("Skip slot exceeds clock slot"))]
     SkipSlotExceedsCurrentSlot,
 
     /// Slot hashes is missing the replayed slot key
-    #[error("Slot hashes is missing the replayed slot key")]
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Slot hashes is missing the replayed slot key")
+    )]
     SlotHashesMissingKey,
 
     /// Version mismatch
-    #[error("Version mismatch")]
+    #[cfg_attr(not(feature = "minimal-errors"), error("Version mismatch"))]
     VersionMismatch,
+
+    /// Block id and replayed bank hash are identical
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Block id matches replayed bank hash")
+    )]
+    BlockIdMatchesReplayedBankHash,
+
+    /// Rank has no associated BLS pubkey
+    #[cfg_attr(not(feature = "minimal-errors"), error("Rank out of range"))]
+    RankOutOfRange,
+
+    /// BLS aggregation failed
+    #[cfg_attr(not(feature = "minimal-errors"), error("Invalid BLS aggregation"))]
+    InvalidBlsAggregation,
+
+    /// Awarding credits would exceed the credit cap
+    #[cfg_attr(not(feature = "minimal-errors"), error("Credits overflow"))]
+    CreditsOverflow,
+
+    /// Notarization vote for a slot that has already been finalized
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Notarization vote for finalized slot")
+    )]
+    NotarizeFinalizedSlot,
+
+    /// The node identity must sign to initialize a vote account
+    #[cfg_attr(not(feature = "minimal-errors"), error("Node identity must sign"))]
+    NodeIdentityMustSign,
+
+    /// Too many slots in a single vote instruction
+    #[cfg_attr(not(feature = "minimal-errors"), error("Too many slots"))]
+    TooManySlots,
+    /// Voting is paused for this vote account
+    #[cfg_attr(not(feature = "minimal-errors"), error("Voting is paused"))]
+    VotingPaused,
+    /// Certificate fields do not match its type
+    #[cfg_attr(not(feature = "minimal-errors"), error("Malformed certificate"))]
+    MalformedCertificate,
+    /// Bytes are not a valid BLS public key
+    #[cfg_attr(not(feature = "minimal-errors"), error("Invalid BLS pubkey"))]
+    InvalidBlsPubkey,
+    /// Notarized slot is newer than any slot in slot hashes
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Notarized slot has not been replayed")
+    )]
+    NotarizeSlotNotReplayed,
+    /// Notarized slot is older than any slot in slot hashes
+    #[cfg_attr(not(feature = "minimal-errors"), error("Notarized slot is too old"))]
+    NotarizeSlotTooOld,
+    /// Authority cannot be used to sign
+    #[cfg_attr(not(feature = "minimal-errors"), error("Invalid authority"))]
+    InvalidAuthority,
+    /// Commission is above the cluster maximum
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Commission exceeds the cluster maximum")
+    )]
+    CommissionExceedsMaximum,
+    /// A pending authorized voter would be overwritten without force
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Pending authorized voter would be overwritten")
+    )]
+    PendingVoterOverwrite,
+    /// A single instruction would award too many credits
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Credits awarded by the transaction exceed the maximum")
+    )]
+    CreditsPerTxExceeded,
+    /// The same account was passed as both the current and new authority
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Current and new authority must be distinct accounts")
+    )]
+    DuplicateAuthorityAccount,
+    /// The newest slot hashes entry is too far behind the clock
+    #[cfg_attr(not(feature = "minimal-errors"), error("Slot hashes sysvar is stale"))]
+    StaleSlotHashes,
+    /// A skip range covers more than `MAX_SKIP_RANGE` slots
+    #[cfg_attr(not(feature = "minimal-errors"), error("Skip range is too large"))]
+    SkipRangeTooLarge,
+    /// A next authorized voter must take effect after epoch 0
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Next authorized voter epoch must be non-zero")
+    )]
+    ZeroNextVoterEpoch,
+    /// The vote state fails `VoteState::check_invariants`
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Vote state violates an invariant")
+    )]
+    InvalidVoteState,
+    /// Commission is above 100 percent
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Commission must be at most 100 percent")
+    )]
+    InvalidCommission,
+    /// Certificate signature does not verify against the ranks in its bitmap
+    #[cfg_attr(
+        not(feature = "minimal-errors"),
+        error("Invalid certificate signature")
+    )]
+    InvalidCertificateSignature,
+    /// Vote account has no node identity
+    #[cfg_attr(not(feature = "minimal-errors"), error("Missing node identity"))]
+    MissingNodeIdentity,
+    /// Bitmap has a bit set beyond the validator count
+    #[cfg_attr(not(feature = "minimal-errors"), error("Bitmap out of range"))]
+    BitmapOutOfRange,
+}
+
+impl VoteError {
+    /// Human readable description of the error
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::ActiveVoteAccountClose => {
+                "Cannot close vote account unless it stopped voting at least one full epoch ago"
+            }
+            Self::CommissionUpdateTooLate => "Cannot update commission at this point in the epoch",
+            Self::InvalidInstruction => "Invalid instruction",
+            Self::InvalidAuthorizeType => "Invalid vote authorize",
+            Self::MissingEpochScheduleSysvar => "Missing epoch schedule sysvar",
+            Self::MissingSlotHashesSysvar => "Missing slot hashes sysvar",
+            Self::ReplayBankHashMismatch => "Replay bank hash mismatch",
+            Self::SkipSlotPresent => "Skipped slot is present on this fork",
+            Self::SkipSlotExceedsCurrentSlot => "Skip slot exceeds clock slot",
+            Self::SlotHashesMissingKey => "Slot hashes is missing the replayed slot key",
+            Self::VersionMismatch => "Version mismatch",
+            Self::BlockIdMatchesReplayedBankHash => "Block id matches replayed bank hash",
+            Self::RankOutOfRange => "Rank out of range",
+            Self::InvalidBlsAggregation => "Invalid BLS aggregation",
+            Self::CreditsOverflow => "Credits overflow",
+            Self::NotarizeFinalizedSlot => "Notarization vote for finalized slot",
+            Self::NodeIdentityMustSign => "Node identity must sign",
+            Self::TooManySlots => "Too many slots",
+            Self::VotingPaused => "Voting is paused",
+            Self::MalformedCertificate => "Malformed certificate",
+            Self::InvalidBlsPubkey => "Invalid BLS pubkey",
+            Self::NotarizeSlotNotReplayed => "Notarized slot has not been replayed",
+            Self::NotarizeSlotTooOld => "Notarized slot is too old",
+            Self::InvalidAuthority => "Invalid authority",
+            Self::CommissionExceedsMaximum => "Commission exceeds the cluster maximum",
+            Self::PendingVoterOverwrite => "Pending authorized voter would be overwritten",
+            Self::CreditsPerTxExceeded => "Credits awarded by the transaction exceed the maximum",
+            Self::DuplicateAuthorityAccount => {
+                "Current and new authority must be distinct accounts"
+            }
+            Self::StaleSlotHashes => "Slot hashes sysvar is stale",
+            Self::SkipRangeTooLarge => "Skip range is too large",
+            Self::ZeroNextVoterEpoch => "Next authorized voter epoch must be non-zero",
+            Self::InvalidVoteState => "Vote state violates an invariant",
+            Self::InvalidCommission => "Commission must be at most 100 percent",
+            Self::InvalidCertificateSignature => "Invalid certificate signature",
+            Self::MissingNodeIdentity => "Missing node identity",
+            Self::BitmapOutOfRange => "Bitmap out of range",
+        }
+    }
 }
 
+#[cfg(feature = "minimal-errors")]
+impl std::fmt::Display for VoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+#[cfg(feature = "minimal-errors")]
+impl std::error::Error for VoteError {}
+
 impl From<VoteError> for ProgramError {
     fn from(e: VoteError) -> Self {
         ProgramError::Custom(e as u32)
@@ -65,3 +258,20 @@ impl<T> DecodeError<T> for VoteError {
         "Vote Error"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use num_traits::FromPrimitive;
+
+    use crate::error::VoteError;
+
+    #[test]
+    fn test_display_matches_message() {
+        let mut code = 0;
+        while let Some(error) = VoteError::from_u32(code) {
+            assert_eq!(error.message(), error.to_string());
+            code += 1;
+        }
+        assert!(code > 0);
+    }
+}
@@ -0,0 +1,83 @@
+//! Fuzzing entrypoint
+
+use {
+    crate::processor::process_instruction,
+    solana_program::{account_info::AccountInfo, entrypoint::ProgramResult},
+    std::panic::{catch_unwind, AssertUnwindSafe},
+};
+
+/// Run `process_instruction` against `accounts` with `data` as the
+/// instruction data, returning `Err` with the panic payload if the program
+/// panicked instead of returning an error
+pub fn fuzz_process_instruction(
+    data: &[u8],
+    accounts: &[AccountInfo],
+) -> std::thread::Result<ProgramResult> {
+    catch_unwind(AssertUnwindSafe(|| {
+        process_instruction(&crate::id(), accounts, data)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{instruction::VoteInstruction, state::VoteState},
+        solana_program::pubkey::Pubkey,
+    };
+
+    #[test]
+    fn test_seed_corpus_does_not_panic() {
+        let corpus: &[&[u8]] = &[
+            &[],
+            &[0xff],
+            &[VoteInstruction::InitializeAccount as u8],
+            &[VoteInstruction::InitializeAccount as u8, 1, 2, 3],
+            &[VoteInstruction::Withdraw as u8, 0xff],
+            &[VoteInstruction::Notarize as u8; 7],
+            &[VoteInstruction::FinalizeMany as u8, 0xff, 0xff, 0xff, 0xff],
+            &[VoteInstruction::FinalizeMany as u8, 2, 0, 0, 0, 1, 0, 0, 0],
+        ];
+        let account_sizes = [0, 1, VoteState::size() - 1, VoteState::size()];
+
+        let program_id = crate::id();
+        let signer_key = Pubkey::new_unique();
+        for size in account_sizes {
+            for data in corpus {
+                let vote_key = Pubkey::new_unique();
+                let mut vote_lamports = 1_000_000;
+                let mut vote_data = vec![0; size];
+                let vote_account = AccountInfo::new(
+                    &vote_key,
+                    false,
+                    true,
+                    &mut vote_lamports,
+                    &mut vote_data,
+                    &program_id,
+                    false,
+                    0,
+                );
+                let mut signer_lamports = 0;
+                let mut signer_data = vec![];
+                let system_program = solana_program::system_program::id();
+                let signer_account = AccountInfo::new(
+                    &signer_key,
+                    true,
+                    false,
+                    &mut signer_lamports,
+                    &mut signer_data,
+                    &system_program,
+                    false,
+                    0,
+                );
+                let accounts = [vote_account, signer_account];
+                for count in 0..=accounts.len() {
+                    assert!(
+                        fuzz_process_instruction(data, &accounts[..count]).is_ok(),
+                        "panicked on {data:?} with {count} accounts and a {size} byte vote account"
+                    );
+                }
+            }
+        }
+    }
+}
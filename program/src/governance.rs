@@ -0,0 +1,113 @@
+//! Governance of the vote program
+//!
+//! Validator ranks and the cluster commission cap are set by the governance
+//! authority, which is the upgrade authority of this program as recorded in its
+//! `ProgramData` account. Governance instructions pass that account so the
+//! authority is read on-chain, and an immutable program has no governance
+
+use {
+    solana_loader_v3_interface::{get_program_data_address, state::UpgradeableLoaderState},
+    solana_program::{
+        account_info::AccountInfo, bpf_loader_upgradeable, program_error::ProgramError,
+        pubkey::Pubkey,
+    },
+};
+
+/// The address of this program's `ProgramData` account
+pub fn program_data_address() -> Pubkey {
+    get_program_data_address(&crate::id())
+}
+
+/// The governance authority recorded in `program_data`, or `None` if the
+/// program is immutable
+pub fn authority(program_data: &AccountInfo) -> Result<Option<Pubkey>, ProgramError> {
+    if *program_data.key != program_data_address() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if !bpf_loader_upgradeable::check_id(program_data.owner) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    let data = program_data.try_borrow_data()?;
+    let metadata = data
+        .get(..UpgradeableLoaderState::size_of_programdata_metadata())
+        .ok_or(ProgramError::InvalidAccountData)?;
+    match bincode::deserialize(metadata) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        }) => Ok(upgrade_authority_address),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Check that `signer` is the governance authority recorded in `program_data`
+pub(crate) fn check_authority(
+    signer: &Pubkey,
+    program_data: &AccountInfo,
+) -> Result<(), ProgramError> {
+    match authority(program_data)? {
+        Some(authority) if authority == *signer => Ok(()),
+        _ => Err(ProgramError::MissingRequiredSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::test_utils::TestAccount};
+
+    #[test]
+    fn test_check_authority() {
+        let authority = Pubkey::new_unique();
+        let mut account = TestAccount::program_data(Some(authority));
+        let program_data = account.info();
+
+        assert_eq!(Ok(Some(authority)), super::authority(&program_data));
+        assert_eq!(Ok(()), check_authority(&authority, &program_data));
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            check_authority(&Pubkey::new_unique(), &program_data)
+        );
+
+        // Only this program's `ProgramData`, owned by the upgradeable loader
+        let mut other = TestAccount {
+            key: Pubkey::new_unique(),
+            ..TestAccount::program_data(Some(authority))
+        };
+        assert_eq!(
+            Err(ProgramError::InvalidArgument),
+            check_authority(&authority, &other.info())
+        );
+        let mut other = TestAccount {
+            owner: crate::id(),
+            ..TestAccount::program_data(Some(authority))
+        };
+        assert_eq!(
+            Err(ProgramError::InvalidAccountOwner),
+            check_authority(&authority, &other.info())
+        );
+    }
+
+    #[test]
+    fn test_check_authority_immutable_program() {
+        let mut account = TestAccount::program_data(None);
+        let program_data = account.info();
+
+        assert_eq!(Ok(None), authority(&program_data));
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            check_authority(&Pubkey::default(), &program_data)
+        );
+
+        // Other loader states are not a `ProgramData`
+        let mut data = bincode::serialize(&UpgradeableLoaderState::Uninitialized).unwrap();
+        data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+        let mut account = TestAccount {
+            data,
+            ..TestAccount::program_data(None)
+        };
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            authority(&account.info())
+        );
+    }
+}
@@ -1,10 +1,12 @@
 //! Program instructions
 
+#[cfg(not(target_os = "solana"))]
+use {crate::vote::Vote, solana_transaction::Transaction};
 use {
     crate::{
         error::VoteError,
-        id,
-        state::{PodSlot, VoteState},
+        governance, id,
+        state::{CommissionConfig, PodSlot, VoteState},
         vote::{
             FinalizationVote, NotarizationFallbackVote, NotarizationVote, SkipFallbackVote,
             SkipVote,
@@ -15,15 +17,17 @@ use {
     num_enum::{IntoPrimitive, TryFromPrimitive},
     solana_bls_signatures::Pubkey as BlsPubkey,
     solana_program::{
+        clock::Slot,
         instruction::{AccountMeta, Instruction},
         program_error::ProgramError,
         pubkey::Pubkey,
         rent::Rent,
-        system_instruction,
+        system_instruction, system_program,
     },
     spl_pod::{
-        bytemuck::{pod_bytes_of, pod_from_bytes, pod_get_packed_len},
-        primitives::{PodU32, PodU64},
+        bytemuck::{pod_bytes_of, pod_from_bytes, pod_get_packed_len, pod_slice_from_bytes},
+        optional_keys::OptionalNonZeroPubkey,
+        primitives::{PodBool, PodU16, PodU32, PodU64},
         slice::PodSlice,
     },
 };
@@ -35,8 +39,9 @@ pub enum VoteInstruction {
     /// Initialize a vote account
     ///
     /// # Account references
-    ///   0. `[WRITE]` Uninitialized vote account
+    ///   0. `[WRITE]` Uninitialized vote account, whose data must be all zero
     ///   1. `[SIGNER]` New validator identity (node_pubkey)
+    ///   2. `[]` Optional `CommissionConfig` capping the commission
     ///
     ///   Data expected by this instruction:
     ///     `InitializeAccountInstructionData`
@@ -49,7 +54,8 @@ pub enum VoteInstruction {
     ///   1. `[SIGNER]` Vote or withdraw authority
     ///
     ///   Data expected by this instruction:
-    ///     `AuthorizeInstructionData`
+    ///     `AuthorizeInstructionData`, or `AuthorizeWithForceInstructionData`
+    ///     to overwrite a pending authorized voter
     Authorize,
 
     /// Authorize a key to send votes or issue a withdrawal
@@ -102,7 +108,8 @@ pub enum VoteInstruction {
     ///   2. `[SIGNER]` Withdraw authority
     ///
     ///   Data expected by this instruction:
-    ///     `lamports` : `u64`
+    ///     `lamports` : `u64`, or
+    ///     `WithdrawWithMemoInstructionData` to attach a memo that is emitted as return data
     Withdraw,
 
     /// Update the vote account's validator identity (node_pubkey)
@@ -118,6 +125,7 @@ pub enum VoteInstruction {
     /// # Account references
     ///   0. `[WRITE]` Vote account to be updated
     ///   1. `[SIGNER]` Withdraw authority
+    ///   2. `[]` Optional `CommissionConfig` capping the commission
     ///
     ///   Data expected by this instruction:
     ///     `commission` : `u8`
@@ -172,6 +180,259 @@ pub enum VoteInstruction {
     ///   Data expected by this instruction:
     ///     `slot` : `u64`
     SkipFallback,
+
+    /// Set or clear the collector for the commission portion of rewards
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be updated
+    ///   1. `[SIGNER]` Withdraw authority
+    ///
+    ///   Data expected by this instruction:
+    ///     `collector_authority` : `OptionalNonZeroPubkey`
+    SetCollectorAuthority,
+
+    /// Finalization votes for several strictly increasing slots, at most
+    /// `MAX_VOTE_MANY_SLOTS`
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be updated
+    ///   1. `[SIGNER]` Vote authority
+    ///
+    ///   Data expected by this instruction:
+    ///     `slots` : `PodSlice<PodSlot>`
+    FinalizeMany,
+
+    /// Pause or resume voting on the vote account. While paused, all votes
+    /// are rejected
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be updated
+    ///   1. `[SIGNER]` Withdraw authority
+    ///
+    ///   Data expected by this instruction:
+    ///     `paused` : `PodBool`
+    SetPaused,
+
+    /// Shrink an over-allocated vote account to `VoteState::size()` and
+    /// refund the rent freed to a recipient
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be shrunk
+    ///   1. `[WRITE]` Recipient account
+    ///   2. `[SIGNER]` Withdraw authority
+    Realloc,
+
+    /// Assign the rank under which the validator signs certificates
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be updated
+    ///   1. `[SIGNER]` Governance authority, the upgrade authority of this program
+    ///   2. `[]` `ProgramData` account of this program, `governance::program_data_address()`
+    ///
+    ///   Data expected by this instruction:
+    ///     `rank` : `PodU16`
+    AssignRank,
+
+    /// Create or update the cluster `CommissionConfig`. Until it is created,
+    /// commissions are not capped
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` `CommissionConfig` account, `derive_commission_config()`
+    ///   1. `[SIGNER]` Governance authority, the upgrade authority of this program
+    ///   2. `[]` `ProgramData` account of this program, `governance::program_data_address()`
+    ///   3. `[WRITE, SIGNER]` Payer of the rent of a new `CommissionConfig`
+    ///   4. `[]` System program
+    ///
+    ///   Data expected by this instruction:
+    ///     `CommissionConfig`
+    SetCommissionConfig,
+
+    /// Zero the vote state of an account that has not earned credits for at
+    /// least one full epoch, leaving its lamports so it can be initialized again
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be deinitialized
+    ///   1. `[SIGNER]` Withdraw authority
+    Deinitialize,
+
+    /// View-only: write the `EpochCredit` of the vote account, its epoch,
+    /// credits and previous credits, as return data for a CPI caller. The
+    /// account is not modified and no signature is required
+    ///
+    /// # Account references
+    ///   0. `[]` Vote account to be read
+    GetCredits,
+
+    /// Update several configuration fields of the vote account at once. Only
+    /// the fields flagged in `UpdateConfigFields` are written, with the same
+    /// checks as the single-field instructions, and either all of them are
+    /// applied or none
+    ///
+    /// # Account references
+    ///   0. `[WRITE]` Vote account to be updated
+    ///   1. `[SIGNER]` Withdraw authority
+    ///   2. `[SIGNER]` New validator identity (node_pubkey), only if
+    ///      `UpdateConfigFields::NODE_PUBKEY` is set
+    ///   3. `[]` Optional `CommissionConfig` capping the commission
+    ///
+    ///   Data expected by this instruction:
+    ///     `UpdateConfigInstructionData`
+    UpdateConfig,
+}
+
+/// The maximum number of slots in a single instruction voting on many slots
+pub const MAX_VOTE_MANY_SLOTS: usize = 32;
+
+impl VoteInstruction {
+    /// Index of the account that signs as the vote authority, if this
+    /// instruction is a vote
+    pub fn vote_authority_account_index(&self) -> Option<usize> {
+        match self {
+            Self::Notarize
+            | Self::Finalize
+            | Self::Skip
+            | Self::NotarizeFallback
+            | Self::SkipFallback
+            | Self::FinalizeMany => Some(1),
+            Self::InitializeAccount
+            | Self::Authorize
+            | Self::AuthorizeChecked
+            | Self::AuthorizeWithSeed
+            | Self::AuthorizeCheckedWithSeed
+            | Self::Withdraw
+            | Self::UpdateValidatorIdentity
+            | Self::UpdateCommission
+            | Self::SetCollectorAuthority
+            | Self::SetPaused
+            | Self::Realloc
+            | Self::AssignRank
+            | Self::SetCommissionConfig
+            | Self::Deinitialize
+            | Self::GetCredits
+            | Self::UpdateConfig => None,
+        }
+    }
+
+    /// The ordered account metas this instruction expects, with signer and
+    /// writable flags set. Fails with `NotEnoughAccountKeys` if a key the
+    /// instruction requires is missing from `keys`. The node signer of
+    /// `UpdateConfig` is only included when `keys.node` is set
+    pub fn account_metas(
+        &self,
+        keys: &VoteInstructionKeys,
+    ) -> Result<Vec<AccountMeta>, ProgramError> {
+        let key = |key: Option<Pubkey>| key.ok_or(ProgramError::NotEnoughAccountKeys);
+        let vote_account =
+            || Ok::<_, ProgramError>(AccountMeta::new(key(keys.vote_account)?, false));
+        let authority =
+            || Ok::<_, ProgramError>(AccountMeta::new_readonly(key(keys.authority)?, true));
+        Ok(match self {
+            Self::InitializeAccount => vec![
+                vote_account()?,
+                AccountMeta::new_readonly(key(keys.node)?, true),
+            ],
+            Self::Authorize
+            | Self::AuthorizeWithSeed
+            | Self::UpdateCommission
+            | Self::Notarize
+            | Self::Finalize
+            | Self::Skip
+            | Self::NotarizeFallback
+            | Self::SkipFallback
+            | Self::SetCollectorAuthority
+            | Self::FinalizeMany
+            | Self::SetPaused
+            | Self::Deinitialize => vec![vote_account()?, authority()?],
+            Self::AssignRank => vec![
+                vote_account()?,
+                authority()?,
+                AccountMeta::new_readonly(key(keys.program_data)?, false),
+            ],
+            Self::AuthorizeChecked | Self::AuthorizeCheckedWithSeed => vec![
+                vote_account()?,
+                authority()?,
+                AccountMeta::new_readonly(key(keys.new_authority)?, true),
+            ],
+            Self::Withdraw | Self::Realloc => vec![
+                vote_account()?,
+                AccountMeta::new(key(keys.recipient)?, false),
+                authority()?,
+            ],
+            Self::UpdateValidatorIdentity => vec![
+                vote_account()?,
+                AccountMeta::new_readonly(key(keys.node)?, true),
+                authority()?,
+            ],
+            Self::SetCommissionConfig => vec![
+                AccountMeta::new(key(keys.commission_config)?, false),
+                authority()?,
+                AccountMeta::new_readonly(key(keys.program_data)?, false),
+                AccountMeta::new(key(keys.payer)?, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            Self::GetCredits => vec![AccountMeta::new_readonly(key(keys.vote_account)?, false)],
+            Self::UpdateConfig => {
+                let mut accounts = vec![vote_account()?, authority()?];
+                if let Some(node) = keys.node {
+                    accounts.push(AccountMeta::new_readonly(node, true));
+                }
+                accounts
+            }
+        })
+    }
+
+    /// The number of accounts this instruction requires, as listed by
+    /// `account_metas`
+    pub fn expected_account_count(&self) -> usize {
+        match self {
+            Self::GetCredits => 1,
+            Self::InitializeAccount
+            | Self::Authorize
+            | Self::AuthorizeWithSeed
+            | Self::UpdateCommission
+            | Self::Notarize
+            | Self::Finalize
+            | Self::Skip
+            | Self::NotarizeFallback
+            | Self::SkipFallback
+            | Self::SetCollectorAuthority
+            | Self::FinalizeMany
+            | Self::SetPaused
+            | Self::Deinitialize
+            | Self::UpdateConfig => 2,
+            Self::AuthorizeChecked
+            | Self::AuthorizeCheckedWithSeed
+            | Self::Withdraw
+            | Self::Realloc
+            | Self::UpdateValidatorIdentity
+            | Self::AssignRank => 3,
+            Self::SetCommissionConfig => 5,
+        }
+    }
+}
+
+/// Pubkeys used to assemble the accounts of a `VoteInstruction`, see
+/// `VoteInstruction::account_metas`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VoteInstructionKeys {
+    /// The vote account
+    pub vote_account: Option<Pubkey>,
+    /// The signing authority: the vote or withdraw authority, or the base key
+    /// of a derived authority
+    pub authority: Option<Pubkey>,
+    /// The new authority for the checked authorize instructions
+    pub new_authority: Option<Pubkey>,
+    /// The validator identity (node_pubkey)
+    pub node: Option<Pubkey>,
+    /// The withdrawal recipient
+    pub recipient: Option<Pubkey>,
+    /// The `ProgramData` account of this program, see
+    /// `governance::program_data_address`
+    pub program_data: Option<Pubkey>,
+    /// The cluster `CommissionConfig`, see `derive_commission_config`
+    pub commission_config: Option<Pubkey>,
+    /// The payer of a new account
+    pub payer: Option<Pubkey>,
 }
 
 /// Instruction builder to create a notarization vote
@@ -216,6 +477,26 @@ pub fn finalize(
     )
 }
 
+/// Instruction builder to create finalization votes for several slots. The
+/// slots must be strictly increasing, and at most `MAX_VOTE_MANY_SLOTS`
+pub fn finalize_many(
+    vote_pubkey: Pubkey,
+    vote_authority: Pubkey,
+    slots: &[Slot],
+) -> Result<Instruction, ProgramError> {
+    check_vote_many_slots(slots)?;
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(vote_authority, true),
+    ];
+
+    Ok(encode_slots_instruction(
+        accounts,
+        VoteInstruction::FinalizeMany,
+        slots,
+    ))
+}
+
 /// Instruction builder to create a skip vote
 pub fn skip(vote_pubkey: Pubkey, vote_authority: Pubkey, vote: &SkipVote) -> Instruction {
     let accounts = vec![
@@ -271,7 +552,7 @@ pub fn skip_fallback(
 /// Data expected by
 /// `VoteInstruction::InitializeAccount`
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
 pub struct InitializeAccountInstructionData {
     /// The node that votes in this account
     pub node_pubkey: Pubkey,
@@ -283,6 +564,10 @@ pub struct InitializeAccountInstructionData {
     pub commission: u8,
     /// BLS public key
     pub bls_pubkey: BlsPubkey,
+    /// Stake pool this account belongs to, if any. This field extends the
+    /// instruction data by 32 bytes, so clients must send it even for
+    /// standalone accounts, as zero
+    pub pool_marker: OptionalNonZeroPubkey,
 }
 
 /// Instruction builder to initialize a new vote account with a valid VoteState:
@@ -304,6 +589,49 @@ pub fn initialize_account(
     )
 }
 
+/// Derive the vote account address and bump seed for a node identity. This program
+/// cannot create accounts at the derived address, so these must be allocated and
+/// assigned to it outside of this program, e.g. at genesis, before `initialize_account`
+pub fn derive_vote_account(node_pubkey: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vote", node_pubkey.as_ref()], &id())
+}
+
+/// Seed of the `CommissionConfig` address, see `derive_commission_config`
+pub(crate) const COMMISSION_CONFIG_SEED: &[u8] = b"commission_config";
+
+/// Derive the address of the governance-set `CommissionConfig` and its bump seed.
+/// When passed after the required accounts of `InitializeAccount` or
+/// `UpdateCommission`, commissions above its maximum are rejected once
+/// `SetCommissionConfig` has created it
+pub fn derive_commission_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COMMISSION_CONFIG_SEED], &id())
+}
+
+/// Instruction builder to create or update the cluster `CommissionConfig`,
+/// signed by the governance authority
+/// - `governance_authority` the upgrade authority of this program
+/// - `payer` funds the rent of the account when it is created
+/// - `max_commission` the highest commission a vote account may set
+pub fn set_commission_config(
+    governance_authority: Pubkey,
+    payer: Pubkey,
+    max_commission: u8,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(derive_commission_config().0, false),
+        AccountMeta::new_readonly(governance_authority, true),
+        AccountMeta::new_readonly(governance::program_data_address(), false),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    encode_instruction(
+        accounts,
+        VoteInstruction::SetCommissionConfig,
+        &CommissionConfig { max_commission },
+    )
+}
+
 /// Instruction builder to create and initialize a new vote account with a valid VoteState:
 /// - `from_pubkey` the account that funds the rent exemption
 /// - `vote_pubkey` the vote account
@@ -372,6 +700,19 @@ pub struct AuthorizeInstructionData {
     pub authority_type: u8,
 }
 
+/// Data expected by
+/// `VoteInstruction::Authorize` when a pending authorized voter may be overwritten
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct AuthorizeWithForceInstructionData {
+    /// New authority pubkey for the vote account
+    pub new_authorized_pubkey: Pubkey,
+    /// The type of authority
+    pub authority_type: u8,
+    /// Whether a pending authorized voter may be overwritten
+    pub force: PodBool,
+}
+
 /// Instruction builder to update the authority of a vote account
 /// - `vote_pubkey` the vote account
 /// - `authorized_pubkey` the current authority
@@ -398,6 +739,34 @@ pub fn authorize(
     )
 }
 
+/// Instruction builder to update the authority of a vote account, overwriting
+/// the next authorized voter even if it has not taken effect yet
+/// - `vote_pubkey` the vote account
+/// - `authorized_pubkey` the current authority
+/// - `new_authorized_pubkey` the new authority
+/// - `authority_type` the type of the authorities
+pub fn force_authorize(
+    vote_pubkey: Pubkey,
+    authorized_pubkey: Pubkey, // currently authorized
+    new_authorized_pubkey: Pubkey,
+    authority_type: AuthorityType,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(authorized_pubkey, true),
+    ];
+
+    encode_instruction(
+        accounts,
+        VoteInstruction::Authorize,
+        &AuthorizeWithForceInstructionData {
+            new_authorized_pubkey,
+            authority_type: u8::from(authority_type),
+            force: PodBool::from(true),
+        },
+    )
+}
+
 /// Instruction builder to update the authority of a vote account
 /// This checked variant requires `new_authorized_pubkey` to be a signer
 /// - `vote_pubkey` the vote account
@@ -534,6 +903,110 @@ pub fn withdraw(
     encode_instruction(accounts, VoteInstruction::Withdraw, &PodU64::from(lamports))
 }
 
+/// Instruction builder to assign the rank of a vote account, signed by the
+/// governance authority
+/// - `vote_pubkey` the vote account
+/// - `governance_authority` the upgrade authority of this program
+/// - `rank` the position of the validator in certificate bitmaps
+pub fn assign_rank(vote_pubkey: Pubkey, governance_authority: Pubkey, rank: u16) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(governance_authority, true),
+        AccountMeta::new_readonly(governance::program_data_address(), false),
+    ];
+
+    encode_instruction(accounts, VoteInstruction::AssignRank, &PodU16::from(rank))
+}
+
+/// Instruction builder to deinitialize an idle vote account
+/// - `vote_pubkey` the vote account
+/// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
+pub fn deinitialize(vote_pubkey: Pubkey, authorized_withdrawer_pubkey: Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
+    ];
+
+    Instruction {
+        program_id: id(),
+        accounts,
+        data: instruction_data_header(VoteInstruction::Deinitialize),
+    }
+}
+
+/// Instruction builder to read the epoch credits of a vote account, returned
+/// as an `EpochCredit` in the transaction return data
+/// - `vote_pubkey` the vote account
+pub fn get_credits(vote_pubkey: Pubkey) -> Instruction {
+    Instruction {
+        program_id: id(),
+        accounts: vec![AccountMeta::new_readonly(vote_pubkey, false)],
+        data: instruction_data_header(VoteInstruction::GetCredits),
+    }
+}
+
+/// Instruction builder to shrink an over-allocated vote account
+/// - `vote_pubkey` the vote account
+/// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
+/// - `recipient` the account to refund the freed rent to
+pub fn realloc(
+    vote_pubkey: Pubkey,
+    authorized_withdrawer_pubkey: Pubkey,
+    recipient_pubkey: Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new(recipient_pubkey, false),
+        AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
+    ];
+
+    Instruction {
+        program_id: id(),
+        accounts,
+        data: instruction_data_header(VoteInstruction::Realloc),
+    }
+}
+
+/// Data expected by
+/// `VoteInstruction::Withdraw` when a memo is attached
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct WithdrawWithMemoInstructionData {
+    /// Amount to withdraw
+    pub lamports: PodU64,
+    /// Reference for audit trails, emitted as return data
+    pub memo: [u8; 32],
+}
+
+/// Instruction builder to withdraw from the vote account with a memo
+/// - `vote_pubkey` the vote account
+/// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
+/// - `lamports` amount to withdraw
+/// - `recipient` the account to withdraw to
+/// - `memo` reference for the withdrawal, emitted as return data
+pub fn withdraw_with_memo(
+    vote_pubkey: Pubkey,
+    authorized_withdrawer_pubkey: Pubkey,
+    lamports: u64,
+    recipient_pubkey: Pubkey,
+    memo: [u8; 32],
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new(recipient_pubkey, false),
+        AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
+    ];
+
+    encode_instruction(
+        accounts,
+        VoteInstruction::Withdraw,
+        &WithdrawWithMemoInstructionData {
+            lamports: PodU64::from(lamports),
+            memo,
+        },
+    )
+}
+
 /// Instruction builder to update the node pubkey on the vote account
 /// - `vote_pubkey` the vote account
 /// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
@@ -549,7 +1022,7 @@ pub fn update_validator_identity(
         AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
     ];
 
-    let data = vec![u8::from(VoteInstruction::UpdateValidatorIdentity)];
+    let data = instruction_data_header(VoteInstruction::UpdateValidatorIdentity);
 
     Instruction {
         program_id: id(),
@@ -575,6 +1048,231 @@ pub fn update_commission(
     encode_instruction(accounts, VoteInstruction::UpdateCommission, &new_commission)
 }
 
+bitflags::bitflags! {
+    /// The fields written by `VoteInstruction::UpdateConfig`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct UpdateConfigFields: u8 {
+        /// Write `UpdateConfigInstructionData::commission`
+        const COMMISSION = 1 << 0;
+        /// Write `UpdateConfigInstructionData::node_pubkey`, which must sign
+        const NODE_PUBKEY = 1 << 1;
+        /// Write `UpdateConfigInstructionData::bls_pubkey`
+        const BLS_PUBKEY = 1 << 2;
+    }
+}
+
+/// Data expected by
+/// `VoteInstruction::UpdateConfig`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct UpdateConfigInstructionData {
+    /// Bitmask of `UpdateConfigFields` selecting the fields to write
+    pub fields: u8,
+    /// The new commission percentage
+    pub commission: u8,
+    /// The new validator identity
+    pub node_pubkey: Pubkey,
+    /// The new BLS public key
+    pub bls_pubkey: BlsPubkey,
+}
+
+impl UpdateConfigInstructionData {
+    /// The fields to write, `InvalidInstructionData` if none or an unknown
+    /// field is flagged
+    pub fn fields(&self) -> Result<UpdateConfigFields, ProgramError> {
+        UpdateConfigFields::from_bits(self.fields)
+            .filter(|fields| !fields.is_empty())
+            .ok_or(ProgramError::InvalidInstructionData)
+    }
+}
+
+/// Instruction builder to update several fields of the vote account at once
+/// - `vote_pubkey` the vote account
+/// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
+/// - `commission` the new commission, `None` to leave it unchanged
+/// - `node_pubkey` the new validator identity, which must sign, `None` to
+///   leave it unchanged
+/// - `bls_pubkey` the new BLS public key, `None` to leave it unchanged
+pub fn update_config(
+    vote_pubkey: Pubkey,
+    authorized_withdrawer_pubkey: Pubkey,
+    commission: Option<u8>,
+    node_pubkey: Option<Pubkey>,
+    bls_pubkey: Option<BlsPubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
+    ];
+    if let Some(node_pubkey) = node_pubkey {
+        accounts.push(AccountMeta::new_readonly(node_pubkey, true));
+    }
+
+    let mut fields = UpdateConfigFields::empty();
+    fields.set(UpdateConfigFields::COMMISSION, commission.is_some());
+    fields.set(UpdateConfigFields::NODE_PUBKEY, node_pubkey.is_some());
+    fields.set(UpdateConfigFields::BLS_PUBKEY, bls_pubkey.is_some());
+
+    encode_instruction(
+        accounts,
+        VoteInstruction::UpdateConfig,
+        &UpdateConfigInstructionData {
+            fields: fields.bits(),
+            commission: commission.unwrap_or_default(),
+            node_pubkey: node_pubkey.unwrap_or_default(),
+            bls_pubkey: bls_pubkey.unwrap_or_default(),
+        },
+    )
+}
+
+/// Instruction builder to set or clear the commission collector of the vote account
+/// - `vote_pubkey` the vote account
+/// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
+/// - `collector_authority` the new collector, `None` to clear it
+pub fn set_collector_authority(
+    vote_pubkey: Pubkey,
+    authorized_withdrawer_pubkey: Pubkey,
+    collector_authority: Option<Pubkey>,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
+    ];
+
+    encode_instruction(
+        accounts,
+        VoteInstruction::SetCollectorAuthority,
+        &OptionalNonZeroPubkey(collector_authority.unwrap_or_default()),
+    )
+}
+
+/// Instruction builder to pause or resume voting on the vote account
+/// - `vote_pubkey` the vote account
+/// - `authorized_withdrawer_pubkey` the withdraw authority of the vote account
+/// - `paused` whether votes should be rejected
+pub fn set_paused(
+    vote_pubkey: Pubkey,
+    authorized_withdrawer_pubkey: Pubkey,
+    paused: bool,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(vote_pubkey, false),
+        AccountMeta::new_readonly(authorized_withdrawer_pubkey, true),
+    ];
+
+    encode_instruction(accounts, VoteInstruction::SetPaused, &PodBool::from(paused))
+}
+
+/// Whether every instruction for `program_id` in `instructions` targets the
+/// same vote account, the first account of each instruction
+#[cfg(not(target_os = "solana"))]
+pub fn all_votes_target_single_account(instructions: &[Instruction], program_id: &Pubkey) -> bool {
+    let mut vote_accounts = instructions
+        .iter()
+        .filter(|instruction| instruction.program_id == *program_id)
+        .map(|instruction| instruction.accounts.first().map(|meta| meta.pubkey));
+    match vote_accounts.next() {
+        Some(Some(vote_account)) => vote_accounts.all(|key| key == Some(vote_account)),
+        Some(None) => false,
+        None => true,
+    }
+}
+
+/// The votes in `transactions` along with the vote account each targets.
+/// Instructions for other programs, non-vote instructions and malformed
+/// votes are skipped
+#[cfg(not(target_os = "solana"))]
+pub fn extract_votes<'a>(
+    transactions: impl Iterator<Item = &'a Transaction> + 'a,
+) -> impl Iterator<Item = (Pubkey, Vote)> + 'a {
+    transactions.flat_map(|transaction| {
+        let message = &transaction.message;
+        message.instructions.iter().filter_map(|instruction| {
+            let program_id = message
+                .account_keys
+                .get(usize::from(instruction.program_id_index))?;
+            if *program_id != id() {
+                return None;
+            }
+            let vote = Vote::deserialize_simple_vote(&instruction.data).ok()?;
+            let vote_account = message
+                .account_keys
+                .get(usize::from(*instruction.accounts.first()?))?;
+            Some((*vote_account, vote))
+        })
+    })
+}
+
+/// The slot voted on by the vote instruction `input`, or `None` if it is not a
+/// simple vote. Only the slot is read, the `Vote` is not decoded and nothing is
+/// allocated
+pub fn peek_vote_slot(input: &[u8]) -> Result<Option<Slot>, ProgramError> {
+    let input = strip_discriminator_prefix(input)?;
+    let slot = match decode_instruction_type(input)? {
+        VoteInstruction::Notarize | VoteInstruction::NotarizeFallback => {
+            decode_instruction_data::<NotarizationVoteInstructionData>(input)?.slot
+        }
+        VoteInstruction::Finalize | VoteInstruction::Skip | VoteInstruction::SkipFallback => {
+            *decode_instruction_data::<PodSlot>(input)?
+        }
+        VoteInstruction::InitializeAccount
+        | VoteInstruction::Authorize
+        | VoteInstruction::AuthorizeChecked
+        | VoteInstruction::AuthorizeWithSeed
+        | VoteInstruction::AuthorizeCheckedWithSeed
+        | VoteInstruction::Withdraw
+        | VoteInstruction::UpdateValidatorIdentity
+        | VoteInstruction::UpdateCommission
+        | VoteInstruction::SetCollectorAuthority
+        | VoteInstruction::SetPaused
+        | VoteInstruction::Realloc
+        | VoteInstruction::AssignRank
+        | VoteInstruction::SetCommissionConfig
+        | VoteInstruction::Deinitialize
+        | VoteInstruction::GetCredits
+        | VoteInstruction::UpdateConfig
+        | VoteInstruction::FinalizeMany => return Ok(None),
+    };
+    Ok(Some(Slot::from(slot)))
+}
+
+/// Namespace byte preceding the instruction discriminant when the program is
+/// built with the `discriminator-prefix` feature, so tooling can tell this
+/// program's instructions apart from those of a forked or successor program
+pub const DISCRIMINATOR_PREFIX: u8 = 0xa6;
+
+/// The prefix, if any, followed by the discriminant of `instruction`
+pub(crate) fn instruction_data_header(instruction: VoteInstruction) -> Vec<u8> {
+    let mut data = Vec::with_capacity(2);
+    if cfg!(feature = "discriminator-prefix") {
+        data.push(DISCRIMINATOR_PREFIX);
+    }
+    data.push(u8::from(instruction));
+    data
+}
+
+/// Strip the `DISCRIMINATOR_PREFIX` from instruction data if the program is
+/// built with the `discriminator-prefix` feature, leaving the discriminant
+/// as the first byte
+pub fn strip_discriminator_prefix(data: &[u8]) -> Result<&[u8], ProgramError> {
+    if !cfg!(feature = "discriminator-prefix") {
+        return Ok(data);
+    }
+    data.strip_prefix(&[DISCRIMINATOR_PREFIX])
+        .ok_or(ProgramError::InvalidInstructionData)
+}
+
+/// Decode the instruction type of instruction data in either form, with or
+/// without the `DISCRIMINATOR_PREFIX`, whichever features this crate is built
+/// with. Also returns whether the data was prefixed. This is unambiguous, as
+/// the prefix is not a valid discriminant
+pub fn decode_discriminant(data: &[u8]) -> Result<(VoteInstruction, bool), ProgramError> {
+    match data.strip_prefix(&[DISCRIMINATOR_PREFIX]) {
+        Some(data) => Ok((decode_instruction_type(data)?, true)),
+        None => Ok((decode_instruction_type(data)?, false)),
+    }
+}
+
 /// Utility function for encoding instruction data
 pub(crate) fn encode_instruction<D: Pod>(
     accounts: Vec<AccountMeta>,
@@ -596,7 +1294,7 @@ pub(crate) fn encode_instruction_with_seed<D: Pod>(
     instruction_data: &D,
     seed: Option<&str>,
 ) -> Instruction {
-    let mut data = vec![u8::from(instruction)];
+    let mut data = instruction_data_header(instruction);
     data.extend_from_slice(bytemuck::bytes_of(instruction_data));
     if let Some(seed) = seed {
         let seed_len = PodU32::from(seed.len() as u32);
@@ -609,6 +1307,24 @@ pub(crate) fn encode_instruction_with_seed<D: Pod>(
     }
 }
 
+/// Utility function for encoding a slot list as a pod slice
+pub(crate) fn encode_slots_instruction(
+    accounts: Vec<AccountMeta>,
+    instruction: VoteInstruction,
+    slots: &[Slot],
+) -> Instruction {
+    let mut data = instruction_data_header(instruction);
+    data.extend_from_slice(pod_bytes_of(&PodU32::from(slots.len() as u32)));
+    for slot in slots {
+        data.extend_from_slice(pod_bytes_of(&PodSlot::from(*slot)));
+    }
+    Instruction {
+        program_id: id(),
+        accounts,
+        data,
+    }
+}
+
 /// Utility function for decoding just the instruction type
 pub(crate) fn decode_instruction_type(input: &[u8]) -> Result<VoteInstruction, ProgramError> {
     if input.is_empty() {
@@ -627,6 +1343,86 @@ pub(crate) fn decode_instruction_data<T: Pod>(input_with_type: &[u8]) -> Result<
     }
 }
 
+/// Utility function for decoding `VoteInstruction::Withdraw` data, which
+/// optionally carries a trailing memo
+pub(crate) fn decode_withdraw_instruction_data(
+    input_with_type: &[u8],
+) -> Result<(u64, Option<[u8; 32]>), ProgramError> {
+    if input_with_type.len() == pod_get_packed_len::<PodU64>().saturating_add(1) {
+        let lamports = decode_instruction_data::<PodU64>(input_with_type)?;
+        Ok((u64::from(*lamports), None))
+    } else {
+        let data = decode_instruction_data::<WithdrawWithMemoInstructionData>(input_with_type)?;
+        Ok((u64::from(data.lamports), Some(data.memo)))
+    }
+}
+
+/// Check a slot list voted on by a single instruction: 1 to
+/// `MAX_VOTE_MANY_SLOTS` slots, strictly increasing so without duplicates
+pub(crate) fn check_vote_many_slots<T: Copy + Into<Slot>>(slots: &[T]) -> Result<(), ProgramError> {
+    let increasing = slots.windows(2).all(|pair| {
+        let [previous, slot]: [Slot; 2] = [pair[0].into(), pair[1].into()];
+        previous < slot
+    });
+    if slots.is_empty() || !increasing {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if slots.len() > MAX_VOTE_MANY_SLOTS {
+        return Err(VoteError::TooManySlots.into());
+    }
+    Ok(())
+}
+
+/// Utility function for decoding `VoteInstruction::Authorize` data, which
+/// optionally carries a trailing force flag
+pub(crate) fn decode_authorize_instruction_data(
+    input_with_type: &[u8],
+) -> Result<(AuthorizeInstructionData, bool), ProgramError> {
+    if input_with_type.len() == pod_get_packed_len::<AuthorizeInstructionData>().saturating_add(1) {
+        let data = decode_instruction_data::<AuthorizeInstructionData>(input_with_type)?;
+        Ok((*data, false))
+    } else {
+        let data = decode_instruction_data::<AuthorizeWithForceInstructionData>(input_with_type)?;
+        Ok((
+            AuthorizeInstructionData {
+                new_authorized_pubkey: data.new_authorized_pubkey,
+                authority_type: data.authority_type,
+            },
+            bool::from(data.force),
+        ))
+    }
+}
+
+/// The authority type of the `VoteInstruction::AuthorizeChecked` instruction
+/// `input`, which is encoded as a bare `u8` after the discriminant
+pub fn decode_authorize_checked_type(input: &[u8]) -> Result<AuthorityType, ProgramError> {
+    let input = strip_discriminator_prefix(input)?;
+    if decode_instruction_type(input)? != VoteInstruction::AuthorizeChecked {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    AuthorityType::try_from(*decode_instruction_data::<u8>(input)?)
+        .map_err(|_| VoteError::InvalidAuthorizeType.into())
+}
+
+/// Utility function for decoding a slot list. The slots are checked by the
+/// processor, see `check_vote_many_slots`
+pub(crate) fn decode_slots_instruction_data(
+    input_with_type: &[u8],
+) -> Result<&[PodSlot], ProgramError> {
+    let length_offset = pod_get_packed_len::<PodU32>().saturating_add(1);
+    if input_with_type.len() < length_offset {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let length = u32::from(*pod_from_bytes::<PodU32>(
+        &input_with_type[1..length_offset],
+    )?) as usize;
+    let slots = pod_slice_from_bytes::<PodSlot>(&input_with_type[length_offset..])?;
+    if slots.len() != length {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(slots)
+}
+
 /// Utility function for decoding instruction data with a variable length seed
 pub(crate) fn decode_instruction_data_with_seed<T: Pod>(
     input_with_type: &[u8],
@@ -642,3 +1438,553 @@ pub(crate) fn decode_instruction_data_with_seed<T: Pod>(
     let seed = PodSlice::unpack(&input_with_type[data_offset..])?;
     Ok((instruction_data, seed))
 }
+
+#[cfg(test)]
+mod tests {
+    use solana_bls_signatures::Pubkey as BlsPubkey;
+    use solana_program::clock::Slot;
+    use solana_program::hash::Hash;
+    use solana_program::instruction::{AccountMeta, Instruction};
+    use solana_program::program_error::ProgramError;
+    use solana_program::pubkey::Pubkey;
+    use solana_program::system_instruction;
+    use solana_transaction::Transaction;
+    use spl_pod::optional_keys::OptionalNonZeroPubkey;
+
+    use crate::error::VoteError;
+    use crate::instruction::{
+        self, decode_discriminant, decode_instruction_data, decode_instruction_type,
+        strip_discriminator_prefix, AuthorityType, AuthorizeInstructionData,
+        InitializeAccountInstructionData, UpdateConfigFields, UpdateConfigInstructionData,
+        VoteInstruction, VoteInstructionKeys, DISCRIMINATOR_PREFIX,
+    };
+    use crate::vote::{FinalizationVote, NotarizationVote, SkipVote, Vote};
+    use crate::vote_processor::NOTARIZATION_VOTE_DATA_LEN;
+
+    fn instruction_data(ix: &Instruction) -> &[u8] {
+        strip_discriminator_prefix(&ix.data).unwrap()
+    }
+
+    #[test]
+    fn test_vote_authority_account_index() {
+        let votes = [
+            VoteInstruction::Notarize,
+            VoteInstruction::Finalize,
+            VoteInstruction::Skip,
+            VoteInstruction::NotarizeFallback,
+            VoteInstruction::SkipFallback,
+            VoteInstruction::FinalizeMany,
+        ];
+        for instruction in (0..=u8::MAX).filter_map(|tag| VoteInstruction::try_from(tag).ok()) {
+            let expected = votes.contains(&instruction).then_some(1);
+            assert_eq!(expected, instruction.vote_authority_account_index());
+        }
+
+        // The index matches where the builders place the vote authority
+        let vote_pubkey = Pubkey::new_unique();
+        let vote_authority = Pubkey::new_unique();
+        for ix in [
+            instruction::notarize(
+                vote_pubkey,
+                vote_authority,
+                &NotarizationVote::new(1, Hash::new_unique(), 0, Hash::new_unique()),
+            ),
+            instruction::finalize(vote_pubkey, vote_authority, &FinalizationVote::new(1)),
+            instruction::skip(vote_pubkey, vote_authority, &SkipVote::new(1)),
+            instruction::finalize_many(vote_pubkey, vote_authority, &[1, 2]).unwrap(),
+        ] {
+            let index = decode_instruction_type(strip_discriminator_prefix(&ix.data).unwrap())
+                .unwrap()
+                .vote_authority_account_index()
+                .unwrap();
+            assert_eq!(vote_authority, ix.accounts[index].pubkey);
+            assert!(ix.accounts[index].is_signer);
+        }
+    }
+
+    #[test]
+    fn test_decode_discriminant() {
+        let unprefixed = [u8::from(VoteInstruction::Finalize), 1, 2];
+        let prefixed = [
+            DISCRIMINATOR_PREFIX,
+            u8::from(VoteInstruction::Finalize),
+            1,
+            2,
+        ];
+        assert!(VoteInstruction::try_from(DISCRIMINATOR_PREFIX).is_err());
+
+        // Both forms are decoded regardless of the feature
+        assert_eq!(
+            Ok((VoteInstruction::Finalize, false)),
+            decode_discriminant(&unprefixed)
+        );
+        assert_eq!(
+            Ok((VoteInstruction::Finalize, true)),
+            decode_discriminant(&prefixed)
+        );
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            decode_discriminant(&[DISCRIMINATOR_PREFIX])
+        );
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            decode_discriminant(&[])
+        );
+
+        // Builders and the program honor the feature
+        let ix = instruction::finalize(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &FinalizationVote::new(1),
+        );
+        let prefixed_build = cfg!(feature = "discriminator-prefix");
+        assert_eq!(
+            Ok((VoteInstruction::Finalize, prefixed_build)),
+            decode_discriminant(&ix.data)
+        );
+        assert_eq!(
+            Ok(VoteInstruction::Finalize),
+            decode_instruction_type(strip_discriminator_prefix(&ix.data).unwrap())
+        );
+        if prefixed_build {
+            assert_eq!(&unprefixed, strip_discriminator_prefix(&prefixed).unwrap());
+            assert_eq!(
+                Err(ProgramError::InvalidInstructionData),
+                strip_discriminator_prefix(&unprefixed)
+            );
+        } else {
+            assert_eq!(&prefixed, strip_discriminator_prefix(&prefixed).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_derive_vote_account() {
+        let node_pubkey = Pubkey::new_unique();
+        let (vote_pubkey, bump) = instruction::derive_vote_account(&node_pubkey);
+        assert_eq!(
+            (vote_pubkey, bump),
+            instruction::derive_vote_account(&node_pubkey)
+        );
+        assert_eq!(
+            Ok(vote_pubkey),
+            Pubkey::create_program_address(&[b"vote", node_pubkey.as_ref(), &[bump]], &crate::id())
+        );
+        assert_ne!(
+            vote_pubkey,
+            instruction::derive_vote_account(&Pubkey::new_unique()).0
+        );
+    }
+
+    #[test]
+    fn test_account_metas() {
+        let vote_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let node = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let keys = VoteInstructionKeys {
+            vote_account: Some(vote_account),
+            authority: Some(authority),
+            new_authority: Some(new_authority),
+            node: Some(node),
+            recipient: Some(recipient),
+            program_data: Some(crate::governance::program_data_address()),
+            commission_config: Some(instruction::derive_commission_config().0),
+            payer: Some(recipient),
+        };
+
+        for ix in [
+            instruction::initialize_account(
+                vote_account,
+                &InitializeAccountInstructionData {
+                    node_pubkey: node,
+                    authorized_voter: Pubkey::new_unique(),
+                    authorized_withdrawer: Pubkey::new_unique(),
+                    commission: 0,
+                    bls_pubkey: BlsPubkey::default(),
+                    pool_marker: OptionalNonZeroPubkey::default(),
+                },
+            ),
+            instruction::authorize(vote_account, authority, new_authority, AuthorityType::Voter),
+            instruction::force_authorize(
+                vote_account,
+                authority,
+                new_authority,
+                AuthorityType::Voter,
+            ),
+            instruction::authorize_checked(
+                vote_account,
+                authority,
+                new_authority,
+                AuthorityType::Withdrawer,
+            ),
+            instruction::authorize_with_seed(
+                vote_account,
+                authority,
+                Pubkey::new_unique(),
+                "seed",
+                new_authority,
+                AuthorityType::Voter,
+            ),
+            instruction::authorize_checked_with_seed(
+                vote_account,
+                authority,
+                Pubkey::new_unique(),
+                "seed",
+                new_authority,
+                AuthorityType::Voter,
+            ),
+            instruction::withdraw(vote_account, authority, 42, recipient),
+            instruction::update_validator_identity(vote_account, authority, node),
+            instruction::update_commission(vote_account, authority, 10),
+            instruction::set_collector_authority(vote_account, authority, None),
+            instruction::set_paused(vote_account, authority, true),
+            instruction::realloc(vote_account, authority, recipient),
+            instruction::assign_rank(vote_account, authority, 7),
+            instruction::deinitialize(vote_account, authority),
+            instruction::get_credits(vote_account),
+            instruction::update_config(vote_account, authority, Some(10), Some(node), None),
+            instruction::finalize(vote_account, authority, &FinalizationVote::new(1)),
+            instruction::skip(vote_account, authority, &SkipVote::new(1)),
+            instruction::finalize_many(vote_account, authority, &[1, 2]).unwrap(),
+            instruction::set_commission_config(authority, recipient, 10),
+        ] {
+            let instruction =
+                decode_instruction_type(strip_discriminator_prefix(&ix.data).unwrap()).unwrap();
+            assert_eq!(Ok(ix.accounts), instruction.account_metas(&keys));
+        }
+
+        // The node identity only signs `UpdateConfig` when it is updated
+        let keys_without_node = VoteInstructionKeys { node: None, ..keys };
+        assert_eq!(
+            Ok(instruction::update_config(vote_account, authority, Some(10), None, None).accounts),
+            VoteInstruction::UpdateConfig.account_metas(&keys_without_node)
+        );
+
+        // Every instruction requires as many accounts as it lists, without the
+        // optional node signer of `UpdateConfig`
+        for instruction in (0..=u8::MAX).filter_map(|tag| VoteInstruction::try_from(tag).ok()) {
+            let keys = if instruction == VoteInstruction::UpdateConfig {
+                keys_without_node
+            } else {
+                keys
+            };
+            assert_eq!(
+                instruction.expected_account_count(),
+                instruction.account_metas(&keys).unwrap().len(),
+                "{instruction:?}"
+            );
+        }
+
+        // Keys the instruction does not use may be omitted
+        let metas = VoteInstruction::InitializeAccount.account_metas(&VoteInstructionKeys {
+            vote_account: Some(vote_account),
+            node: Some(node),
+            ..VoteInstructionKeys::default()
+        });
+        assert_eq!(
+            Ok(vec![
+                AccountMeta::new(vote_account, false),
+                AccountMeta::new_readonly(node, true),
+            ]),
+            metas
+        );
+
+        // Missing required keys are an error
+        let metas = VoteInstruction::Withdraw.account_metas(&VoteInstructionKeys {
+            vote_account: Some(vote_account),
+            authority: Some(authority),
+            ..VoteInstructionKeys::default()
+        });
+        assert_eq!(Err(ProgramError::NotEnoughAccountKeys), metas);
+    }
+
+    #[test]
+    fn test_decode_withdraw_instruction_data() {
+        let recipient = Pubkey::new_unique();
+        let ix = instruction::withdraw(Pubkey::new_unique(), Pubkey::new_unique(), 42, recipient);
+        assert_eq!(
+            Ok((42, None)),
+            instruction::decode_withdraw_instruction_data(instruction_data(&ix))
+        );
+
+        let memo = [7; 32];
+        let ix = instruction::withdraw_with_memo(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            42,
+            recipient,
+            memo,
+        );
+        assert_eq!(
+            Ok((42, Some(memo))),
+            instruction::decode_withdraw_instruction_data(instruction_data(&ix))
+        );
+
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            instruction::decode_withdraw_instruction_data(&instruction_data(&ix)[..20])
+        );
+    }
+
+    #[test]
+    fn test_decode_authorize_instruction_data() {
+        let new_authority = Pubkey::new_unique();
+        let expected = AuthorizeInstructionData {
+            new_authorized_pubkey: new_authority,
+            authority_type: u8::from(AuthorityType::Voter),
+        };
+
+        let ix = instruction::authorize(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            new_authority,
+            AuthorityType::Voter,
+        );
+        assert_eq!(
+            Ok((expected, false)),
+            instruction::decode_authorize_instruction_data(instruction_data(&ix))
+        );
+
+        let ix = instruction::force_authorize(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            new_authority,
+            AuthorityType::Voter,
+        );
+        assert_eq!(
+            Ok((expected, true)),
+            instruction::decode_authorize_instruction_data(instruction_data(&ix))
+        );
+
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            instruction::decode_authorize_instruction_data(&instruction_data(&ix)[..20])
+        );
+    }
+
+    #[test]
+    fn test_decode_authorize_checked_type() {
+        for authority_type in [AuthorityType::Voter, AuthorityType::Withdrawer] {
+            let ix = instruction::authorize_checked(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                authority_type,
+            );
+            assert_eq!(
+                Ok(authority_type),
+                instruction::decode_authorize_checked_type(&ix.data)
+            );
+        }
+
+        // Other instructions are rejected, even with the same data layout
+        let ix = instruction::authorize(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            AuthorityType::Voter,
+        );
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            instruction::decode_authorize_checked_type(&ix.data)
+        );
+        let mut data = instruction::authorize_checked(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            AuthorityType::Voter,
+        )
+        .data;
+        *data.last_mut().unwrap() = 2;
+        assert_eq!(
+            Err(VoteError::InvalidAuthorizeType.into()),
+            instruction::decode_authorize_checked_type(&data)
+        );
+    }
+
+    #[test]
+    fn test_update_config_fields() {
+        let ix = instruction::update_config(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Some(5),
+            None,
+            Some(BlsPubkey::default()),
+        );
+        let mut data =
+            *decode_instruction_data::<UpdateConfigInstructionData>(instruction_data(&ix)).unwrap();
+        assert_eq!(
+            Ok(UpdateConfigFields::COMMISSION | UpdateConfigFields::BLS_PUBKEY),
+            data.fields()
+        );
+        assert_eq!(5, data.commission);
+
+        // At least one known field must be flagged
+        for fields in [0, 1 << 3, UpdateConfigFields::all().bits() | 1 << 7] {
+            data.fields = fields;
+            assert_eq!(Err(ProgramError::InvalidInstructionData), data.fields());
+        }
+    }
+
+    #[test]
+    fn test_all_votes_target_single_account() {
+        let vote_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let memo = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(Pubkey::new_unique(), true)],
+        );
+
+        let single = [
+            instruction::finalize(vote_account, authority, &FinalizationVote::new(1)),
+            memo.clone(),
+            instruction::skip(vote_account, authority, &SkipVote::new(2)),
+        ];
+        assert!(instruction::all_votes_target_single_account(
+            &single,
+            &crate::id()
+        ));
+        assert!(instruction::all_votes_target_single_account(
+            std::slice::from_ref(&memo),
+            &crate::id()
+        ));
+
+        let mixed = [
+            instruction::finalize(vote_account, authority, &FinalizationVote::new(1)),
+            memo,
+            instruction::skip(Pubkey::new_unique(), authority, &SkipVote::new(2)),
+        ];
+        assert!(!instruction::all_votes_target_single_account(
+            &mixed,
+            &crate::id()
+        ));
+    }
+
+    #[test]
+    fn test_extract_votes() {
+        let payer = Pubkey::new_unique();
+        let vote_account = Pubkey::new_unique();
+        let other_vote_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let notarization_vote = NotarizationVote::new(5, Hash::new_unique(), 0, Hash::new_unique());
+
+        let block = [
+            Transaction::new_with_payer(
+                &[instruction::notarize(
+                    vote_account,
+                    authority,
+                    &notarization_vote,
+                )],
+                Some(&payer),
+            ),
+            Transaction::new_with_payer(
+                &[system_instruction::transfer(
+                    &payer,
+                    &Pubkey::new_unique(),
+                    42,
+                )],
+                Some(&payer),
+            ),
+            Transaction::new_with_payer(
+                &[
+                    instruction::skip(other_vote_account, authority, &SkipVote::new(6)),
+                    instruction::withdraw(vote_account, authority, 1, payer),
+                ],
+                Some(&payer),
+            ),
+        ];
+
+        assert_eq!(
+            vec![
+                (vote_account, Vote::from(notarization_vote)),
+                (other_vote_account, Vote::new_skip_vote(6)),
+            ],
+            instruction::extract_votes(block.iter()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_notarization_vote_data_len() {
+        for vote in [
+            Vote::new_notarization_vote(3, Hash::new_unique(), Hash::new_unique()),
+            Vote::new_notarization_fallback_vote(3, Hash::new_unique(), Hash::new_unique()),
+        ] {
+            let ix = vote.to_vote_instruction(Pubkey::new_unique(), Pubkey::new_unique());
+            assert_eq!(NOTARIZATION_VOTE_DATA_LEN + 1, instruction_data(&ix).len());
+        }
+    }
+
+    #[test]
+    fn test_peek_vote_slot() {
+        let vote_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let votes = [
+            Vote::new_notarization_vote(3, Hash::new_unique(), Hash::new_unique()),
+            Vote::new_finalization_vote(4),
+            Vote::new_skip_vote(5),
+            Vote::new_notarization_fallback_vote(6, Hash::new_unique(), Hash::new_unique()),
+            Vote::new_skip_fallback_vote(7),
+        ];
+        for (vote, slot) in votes.iter().zip(3..) {
+            let ix = vote.to_vote_instruction(vote_account, authority);
+            assert_eq!(Ok(Some(slot)), instruction::peek_vote_slot(&ix.data));
+        }
+
+        for ix in [
+            instruction::withdraw(vote_account, authority, 1, Pubkey::new_unique()),
+            instruction::finalize_many(vote_account, authority, &[8, 9]).unwrap(),
+        ] {
+            assert_eq!(Ok(None), instruction::peek_vote_slot(&ix.data));
+        }
+
+        let mut ix = Vote::new_skip_vote(5).to_vote_instruction(vote_account, authority);
+        ix.data.pop();
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            instruction::peek_vote_slot(&ix.data)
+        );
+    }
+
+    #[test]
+    fn test_finalize_many_instruction_data() {
+        let vote_pubkey = Pubkey::new_unique();
+        let vote_authority = Pubkey::new_unique();
+
+        let slots = [3, 5, 8, 13, 21];
+        let ix = instruction::finalize_many(vote_pubkey, vote_authority, &slots).unwrap();
+        assert_eq!(
+            Ok(VoteInstruction::FinalizeMany),
+            instruction::decode_instruction_type(instruction_data(&ix))
+        );
+        let decoded: Vec<Slot> = instruction::decode_slots_instruction_data(instruction_data(&ix))
+            .unwrap()
+            .iter()
+            .map(|slot| Slot::from(*slot))
+            .collect();
+        assert_eq!(slots.to_vec(), decoded);
+
+        let data = instruction_data(&ix);
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            instruction::decode_slots_instruction_data(&data[..data.len() - 8])
+        );
+
+        // Empty, unsorted, duplicate and over-long slot lists are rejected
+        let max_slots: Vec<Slot> = (0..instruction::MAX_VOTE_MANY_SLOTS as u64).collect();
+        assert!(instruction::finalize_many(vote_pubkey, vote_authority, &max_slots).is_ok());
+        let too_many_slots: Vec<Slot> = (0..=instruction::MAX_VOTE_MANY_SLOTS as u64).collect();
+        for (slots, expected) in [
+            (&[][..], ProgramError::InvalidInstructionData),
+            (&[5, 3][..], ProgramError::InvalidInstructionData),
+            (&[3, 5, 5][..], ProgramError::InvalidInstructionData),
+            (&too_many_slots[..], VoteError::TooManySlots.into()),
+        ] {
+            assert_eq!(
+                Err(expected),
+                instruction::finalize_many(vote_pubkey, vote_authority, slots)
+            );
+        }
+    }
+}
@@ -4,22 +4,45 @@
 #![cfg_attr(feature = "frozen-abi", feature(min_specialization))]
 
 pub mod accounting;
+#[cfg(not(target_os = "solana"))]
+pub mod bls;
 pub mod bls_message;
 pub mod certificate;
+pub mod domains;
 mod entrypoint;
 pub mod error;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod governance;
 pub mod instruction;
 pub mod processor;
 pub mod state;
+#[cfg(test)]
+mod test_utils;
 pub mod vote;
-mod vote_processor;
+pub mod vote_processor;
 
 // Export current SDK types for downstream users building with a different SDK
 // version
 pub use solana_program;
 
+// The canonical id of the Alpenglow vote program, as recorded in
+// `program-id.md`. It intentionally differs from the native vote program id,
+// `solana_program::vote::program::id()`, declared by `solana-vote-interface`
 solana_program::declare_id!("Vote222222222222222222222222222222222222222");
 
 #[cfg_attr(feature = "frozen-abi", macro_use)]
 #[cfg(feature = "frozen-abi")]
 extern crate solana_frozen_abi_macro;
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_program_id() {
+        assert_eq!(
+            include_str!("../program-id.md").trim(),
+            crate::id().to_string()
+        );
+        assert_ne!(solana_program::vote::program::id(), crate::id());
+    }
+}
@@ -6,20 +6,25 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     clock::{self, Clock},
     entrypoint::ProgramResult,
+    msg,
+    program::set_return_data,
     pubkey::Pubkey,
     rent,
     sysvar::Sysvar,
 };
-use spl_pod::primitives::PodU64;
+use spl_pod::optional_keys::OptionalNonZeroPubkey;
+use spl_pod::primitives::{PodBool, PodU16};
 
 use crate::accounting;
 use crate::error::VoteError;
 use crate::instruction::{
-    decode_instruction_data, decode_instruction_data_with_seed, decode_instruction_type,
-    AuthorityType, AuthorizeCheckedWithSeedInstructionData, AuthorizeInstructionData,
-    AuthorizeWithSeedInstructionData, InitializeAccountInstructionData, VoteInstruction,
+    decode_authorize_instruction_data, decode_instruction_data, decode_instruction_data_with_seed,
+    decode_instruction_type, decode_slots_instruction_data, decode_withdraw_instruction_data,
+    strip_discriminator_prefix, AuthorityType, AuthorizeCheckedWithSeedInstructionData,
+    AuthorizeWithSeedInstructionData, InitializeAccountInstructionData, UpdateConfigFields,
+    UpdateConfigInstructionData, VoteInstruction,
 };
-use crate::state::{PodSlot, VoteState};
+use crate::state::{CommissionConfig, PodSlot, VoteState};
 use crate::vote_processor::{self, NotarizationVoteInstructionData};
 
 fn pod_slot_hashes() -> Result<PodSlotHashes, VoteError> {
@@ -36,13 +41,23 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     input: &[u8],
 ) -> ProgramResult {
+    let input = strip_discriminator_prefix(input)?;
     let instruction_type = decode_instruction_type(input)?;
+    check_account_count(instruction_type, accounts)?;
     let account_info_iter = &mut accounts.iter();
 
     let vote_account = next_account_info(account_info_iter)?;
-    if vote_account.owner != program_id {
+    // `SetCommissionConfig` creates its first account, the `CommissionConfig`,
+    // every other instruction operates on a vote account owned by the program
+    if vote_account.owner != program_id && instruction_type != VoteInstruction::SetCommissionConfig
+    {
         return Err(ProgramError::InvalidAccountOwner);
     }
+    if updates_vote_state(instruction_type)
+        && VoteState::needs_migration(&vote_account.try_borrow_data()?)
+    {
+        VoteState::migrate(vote_account, &rent::Rent::get()?)?;
+    }
 
     match instruction_type {
         VoteInstruction::InitializeAccount => {
@@ -53,26 +68,21 @@ pub fn process_instruction(
 
             let clock = clock::Clock::get()?;
 
-            let Some(node_pubkey) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let node_account = next_account_info(account_info_iter)?;
 
             let instruction_data =
                 decode_instruction_data::<InitializeAccountInstructionData>(input)?;
-            if instruction_data.node_pubkey != *node_pubkey {
-                return Err(ProgramError::MissingRequiredSignature);
-            }
+            check_node_identity_signer(node_account, instruction_data)?;
+            let max_commission = accounting::max_commission(account_info_iter.next())?;
 
-            initialize_account(vote_account, instruction_data, &clock)
+            initialize_account(vote_account, instruction_data, max_commission, &clock)
         }
         VoteInstruction::Authorize => {
             let clock = clock::Clock::get()?;
 
-            let Some(authority_pubkey) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
 
-            let instruction_data = decode_instruction_data::<AuthorizeInstructionData>(input)?;
+            let (instruction_data, force) = decode_authorize_instruction_data(input)?;
             let vote_authorize = AuthorityType::try_from(instruction_data.authority_type)
                 .map_err(|_| ProgramError::from(VoteError::InvalidAuthorizeType))?;
             accounting::authorize(
@@ -80,19 +90,19 @@ pub fn process_instruction(
                 &instruction_data.new_authorized_pubkey,
                 vote_authorize,
                 authority_pubkey,
+                force,
                 &clock,
             )
         }
         VoteInstruction::AuthorizeChecked => {
             let clock = clock::Clock::get()?;
 
-            let Some(authority_pubkey) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
-            let Some(new_authority_pubkey) = next_account_info(account_info_iter)?.signer_key()
-            else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+            let new_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+            // A single signature must not stand in for both authorities
+            if authority_pubkey == new_authority_pubkey {
+                return Err(VoteError::DuplicateAuthorityAccount.into());
+            }
 
             let vote_authorize = AuthorityType::try_from(*decode_instruction_data::<u8>(input)?)
                 .map_err(|_| ProgramError::from(VoteError::InvalidAuthorizeType))?;
@@ -102,15 +112,14 @@ pub fn process_instruction(
                 new_authority_pubkey,
                 vote_authorize,
                 authority_pubkey,
+                false,
                 &clock,
             )
         }
         VoteInstruction::AuthorizeWithSeed => {
             let clock = clock::Clock::get()?;
 
-            let Some(base_key) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let base_key = require_signer(next_account_info(account_info_iter)?)?;
 
             let (instruction_data, seed) =
                 decode_instruction_data_with_seed::<AuthorizeWithSeedInstructionData>(input)?;
@@ -130,20 +139,16 @@ pub fn process_instruction(
                 &instruction_data.new_authority,
                 vote_authorize,
                 &authority_pubkey,
+                false,
                 &clock,
             )
         }
         VoteInstruction::AuthorizeCheckedWithSeed => {
             let clock = clock::Clock::get()?;
 
-            let Some(base_key) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let base_key = require_signer(next_account_info(account_info_iter)?)?;
 
-            let Some(new_authority_pubkey) = next_account_info(account_info_iter)?.signer_key()
-            else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let new_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
 
             let (instruction_data, seed) = decode_instruction_data_with_seed::<
                 AuthorizeCheckedWithSeedInstructionData,
@@ -164,6 +169,7 @@ pub fn process_instruction(
                 new_authority_pubkey,
                 vote_authorize,
                 &authority_pubkey,
+                false,
                 &clock,
             )
         }
@@ -172,13 +178,9 @@ pub fn process_instruction(
             let rent = rent::Rent::get()?;
             let clock = clock::Clock::get()?;
 
-            let Some(withdraw_authority_pubkey) =
-                next_account_info(account_info_iter)?.signer_key()
-            else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
 
-            let lamports = u64::from(*decode_instruction_data::<PodU64>(input)?);
+            let (lamports, memo) = decode_withdraw_instruction_data(input)?;
 
             accounting::withdraw(
                 vote_account,
@@ -187,44 +189,131 @@ pub fn process_instruction(
                 withdraw_authority_pubkey,
                 &rent,
                 &clock,
+            )?;
+            if let Some(memo) = memo {
+                set_return_data(&memo);
+            }
+            Ok(())
+        }
+        VoteInstruction::Realloc => {
+            let recipient = next_account_info(account_info_iter)?;
+            let rent = rent::Rent::get()?;
+
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+
+            accounting::realloc(vote_account, recipient, withdraw_authority_pubkey, &rent)
+        }
+        VoteInstruction::Deinitialize => {
+            let clock = clock::Clock::get()?;
+
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+
+            accounting::deinitialize(vote_account, withdraw_authority_pubkey, &clock)
+        }
+        VoteInstruction::GetCredits => {
+            let vote_state = VoteState::deserialize_versioned(&vote_account.try_borrow_data()?)?;
+            set_return_data(bytemuck::bytes_of(vote_state.epoch_credits()));
+            Ok(())
+        }
+        VoteInstruction::AssignRank => {
+            let governance_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+            let program_data = next_account_info(account_info_iter)?;
+
+            let rank = decode_instruction_data::<PodU16>(input)?;
+
+            accounting::assign_rank(vote_account, rank, governance_pubkey, program_data)
+        }
+        VoteInstruction::SetCommissionConfig => {
+            let rent = rent::Rent::get()?;
+
+            let config_account = vote_account;
+            let governance_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+            let program_data = next_account_info(account_info_iter)?;
+            let payer = next_account_info(account_info_iter)?;
+            let system_program = next_account_info(account_info_iter)?;
+
+            let config = decode_instruction_data::<CommissionConfig>(input)?;
+
+            accounting::set_commission_config(
+                config_account,
+                governance_pubkey,
+                program_data,
+                payer,
+                system_program,
+                config,
+                &rent,
             )
         }
         VoteInstruction::UpdateValidatorIdentity => {
-            let Some(new_node_pubkey) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
-            let Some(withdraw_pubkey) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let new_node_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+            let withdraw_pubkey = require_signer(next_account_info(account_info_iter)?)?;
             accounting::update_validator_identity(vote_account, new_node_pubkey, withdraw_pubkey)
         }
         VoteInstruction::UpdateCommission => {
-            let epoch_schedule = epoch_schedule()?;
             let clock = clock::Clock::get()?;
 
-            let Some(withdraw_authority_pubkey) =
-                next_account_info(account_info_iter)?.signer_key()
-            else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
 
             let commission = *decode_instruction_data::<u8>(input)?;
+            let max_commission = accounting::max_commission(account_info_iter.next())?;
 
             accounting::update_commission(
                 vote_account,
                 commission,
                 withdraw_authority_pubkey,
-                &epoch_schedule,
+                epoch_schedule,
+                max_commission,
+                &clock,
+            )
+        }
+        VoteInstruction::UpdateConfig => {
+            let clock = clock::Clock::get()?;
+
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+
+            let instruction_data = decode_instruction_data::<UpdateConfigInstructionData>(input)?;
+            let fields = instruction_data.fields()?;
+            if fields.contains(UpdateConfigFields::NODE_PUBKEY) {
+                let node_account = next_account_info(account_info_iter)?;
+                if node_account.signer_key() != Some(&instruction_data.node_pubkey) {
+                    return Err(VoteError::NodeIdentityMustSign.into());
+                }
+            }
+            let max_commission = accounting::max_commission(account_info_iter.next())?;
+
+            accounting::update_config(
+                vote_account,
+                fields,
+                instruction_data,
+                withdraw_authority_pubkey,
+                epoch_schedule,
+                max_commission,
                 &clock,
             )
         }
+        VoteInstruction::SetCollectorAuthority => {
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+
+            let collector_authority = decode_instruction_data::<OptionalNonZeroPubkey>(input)?;
+
+            accounting::set_collector_authority(
+                vote_account,
+                collector_authority,
+                withdraw_authority_pubkey,
+            )
+        }
+        VoteInstruction::SetPaused => {
+            let withdraw_authority_pubkey = require_signer(next_account_info(account_info_iter)?)?;
+
+            let paused = decode_instruction_data::<PodBool>(input)?;
+
+            accounting::set_paused(vote_account, paused, withdraw_authority_pubkey)
+        }
         VoteInstruction::Notarize => {
             let clock = clock::Clock::get()?;
             let slot_hashes = pod_slot_hashes()?;
 
-            let Some(authority) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority = require_signer(next_account_info(account_info_iter)?)?;
 
             let vote = decode_instruction_data::<NotarizationVoteInstructionData>(input)?;
 
@@ -234,15 +323,14 @@ pub fn process_instruction(
                 &clock,
                 &slot_hashes,
                 vote,
+                VoteInstruction::Notarize,
             )
         }
         VoteInstruction::NotarizeFallback => {
             let clock = clock::Clock::get()?;
             let slot_hashes = pod_slot_hashes()?;
 
-            let Some(authority) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority = require_signer(next_account_info(account_info_iter)?)?;
 
             let vote = decode_instruction_data::<NotarizationVoteInstructionData>(input)?;
 
@@ -252,65 +340,376 @@ pub fn process_instruction(
                 &clock,
                 &slot_hashes,
                 vote,
+                VoteInstruction::NotarizeFallback,
             )
         }
         VoteInstruction::Finalize => {
             let clock = clock::Clock::get()?;
 
-            let Some(authority) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority = require_signer(next_account_info(account_info_iter)?)?;
 
             let vote = decode_instruction_data::<PodSlot>(input)?;
 
             vote_processor::process_finalization_vote(vote_account, authority, &clock, vote)
         }
+        VoteInstruction::FinalizeMany => {
+            let clock = clock::Clock::get()?;
+
+            let authority = require_signer(next_account_info(account_info_iter)?)?;
+
+            let slots = decode_slots_instruction_data(input)?;
+
+            vote_processor::process_finalize_many(vote_account, authority, &clock, slots)
+        }
         VoteInstruction::Skip => {
             let clock = clock::Clock::get()?;
             let slot_hashes = pod_slot_hashes()?;
 
-            let Some(authority) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority = require_signer(next_account_info(account_info_iter)?)?;
 
             let vote = decode_instruction_data::<PodSlot>(input)?;
 
-            vote_processor::process_skip_vote(vote_account, authority, &clock, &slot_hashes, vote)
+            vote_processor::process_skip_vote(
+                vote_account,
+                authority,
+                &clock,
+                &slot_hashes,
+                vote,
+                VoteInstruction::Skip,
+            )
         }
         VoteInstruction::SkipFallback => {
             let clock = clock::Clock::get()?;
             let slot_hashes = pod_slot_hashes()?;
 
-            let Some(authority) = next_account_info(account_info_iter)?.signer_key() else {
-                return Err(ProgramError::MissingRequiredSignature);
-            };
+            let authority = require_signer(next_account_info(account_info_iter)?)?;
 
             let vote = decode_instruction_data::<PodSlot>(input)?;
 
-            vote_processor::process_skip_vote(vote_account, authority, &clock, &slot_hashes, vote)
+            vote_processor::process_skip_vote(
+                vote_account,
+                authority,
+                &clock,
+                &slot_hashes,
+                vote,
+                VoteInstruction::SkipFallback,
+            )
         }
     }
 }
 
+/// Whether `instruction` may update the vote state of its first account, which
+/// is then migrated from an older version first. Withdrawals and
+/// deinitialization only read older versions, so that accounts short of rent
+/// for the current layout can still be emptied
+fn updates_vote_state(instruction: VoteInstruction) -> bool {
+    !matches!(
+        instruction,
+        VoteInstruction::InitializeAccount
+            | VoteInstruction::Withdraw
+            | VoteInstruction::Deinitialize
+            | VoteInstruction::GetCredits
+            | VoteInstruction::SetCommissionConfig
+    )
+}
+
+/// Check that at least as many accounts as `instruction` expects were supplied
+fn check_account_count(
+    instruction: VoteInstruction,
+    accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let expected = instruction.expected_account_count();
+    if accounts.len() < expected {
+        msg!(
+            "{:?} expects {} accounts, got {}",
+            instruction,
+            expected,
+            accounts.len()
+        );
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    Ok(())
+}
+
+/// The key of `account_info` if it signed the transaction, otherwise
+/// `MissingRequiredSignature`
+fn require_signer<'a>(account_info: &'a AccountInfo) -> Result<&'a Pubkey, ProgramError> {
+    account_info
+        .signer_key()
+        .ok_or(ProgramError::MissingRequiredSignature)
+}
+
+/// Check that the node identity in `init_data` signed, either directly or as a
+/// PDA through `invoke_signed`
+fn check_node_identity_signer(
+    node_account: &AccountInfo,
+    init_data: &InitializeAccountInstructionData,
+) -> Result<(), ProgramError> {
+    if node_account.signer_key() != Some(&init_data.node_pubkey) {
+        return Err(VoteError::NodeIdentityMustSign.into());
+    }
+    Ok(())
+}
+
 /// Initialize the vote_state for a vote account
 /// Assumes that the account is being init as part of a account creation or balance transfer and
 /// that the transaction must be signed by the staker's keys
 pub(crate) fn initialize_account(
     vote_account: &AccountInfo,
     init_data: &InitializeAccountInstructionData,
+    max_commission: Option<u8>,
     clock: &Clock,
 ) -> Result<(), ProgramError> {
-    if vote_account.data_len() != std::mem::size_of::<VoteState>() {
+    // Over-allocated accounts are allowed, the vote state occupies the prefix
+    if vote_account.data_len() < VoteState::size() {
         return Err(ProgramError::InvalidAccountData);
     }
+    // Any non-zero byte, not only the version, marks the account as in use, so
+    // that pre-seeded data cannot be initialized over
+    if vote_account
+        .try_borrow_data()?
+        .iter()
+        .any(|byte| *byte != 0)
     {
-        let vote_state = vote_account.data.borrow();
-        let vote_state = bytemuck::from_bytes::<VoteState>(&vote_state);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    accounting::check_max_commission(init_data.commission, max_commission)?;
+
+    let vote_state = VoteState::new(init_data, clock);
+    vote_state.check_invariants()?;
+    VoteState::set_vote_account_state(vote_account, &vote_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_bls_signatures::keypair::Keypair as BlsKeypair;
+    use solana_program::account_info::AccountInfo;
+    use solana_program::clock::Clock;
+    use solana_program::program_error::ProgramError;
+    use solana_program::pubkey::Pubkey;
+    use spl_pod::optional_keys::OptionalNonZeroPubkey;
+
+    use crate::error::VoteError;
+    use crate::instruction::{
+        instruction_data_header, InitializeAccountInstructionData, VoteInstruction,
+    };
+    use crate::processor::{
+        check_node_identity_signer, initialize_account, process_instruction, require_signer,
+    };
+    use crate::state::{offsets, VoteState};
+    use crate::test_utils::TestAccount;
+
+    fn init_data() -> InitializeAccountInstructionData {
+        InitializeAccountInstructionData {
+            node_pubkey: Pubkey::new_unique(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 42,
+            bls_pubkey: BlsKeypair::new().public.into(),
+            pool_marker: OptionalNonZeroPubkey::default(),
+        }
+    }
+
+    fn initialize_with_data(data: &mut [u8]) -> Result<(), ProgramError> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let owner = crate::id();
+        let vote_account =
+            AccountInfo::new(&key, false, true, &mut lamports, data, &owner, false, 0);
+        initialize_account(&vote_account, &init_data(), None, &Clock::default())
+    }
+
+    #[test]
+    fn test_initialize_account_pre_seeded() {
+        // A zero version with a seeded node pubkey
+        let mut data = vec![0; VoteState::size()];
+        data[offsets::NODE_PUBKEY_OFFSET] = 1;
+        assert_eq!(
+            Err(ProgramError::AccountAlreadyInitialized),
+            initialize_with_data(&mut data)
+        );
+
+        // Seeded bytes past the vote state of an over-allocated account
+        let mut data = vec![0; VoteState::size() + 100];
+        *data.last_mut().unwrap() = 1;
+        assert_eq!(
+            Err(ProgramError::AccountAlreadyInitialized),
+            initialize_with_data(&mut data)
+        );
+    }
+
+    #[test]
+    fn test_initialize_account_max_commission() {
+        for (commission, expected) in [
+            (20, Err(VoteError::CommissionExceedsMaximum.into())),
+            (5, Ok(())),
+        ] {
+            let mut account = TestAccount::with_data(vec![0; VoteState::size()]);
+            let init_data = InitializeAccountInstructionData {
+                commission,
+                ..init_data()
+            };
+            assert_eq!(
+                expected,
+                initialize_account(&account.info(), &init_data, Some(10), &Clock::default())
+            );
+        }
+    }
 
-        if vote_state.is_initialized() {
-            return Err(ProgramError::AccountAlreadyInitialized);
+    #[test]
+    fn test_initialize_account_exact_size() {
+        let mut data = vec![0; VoteState::size()];
+        assert_eq!(Ok(()), initialize_with_data(&mut data));
+        assert!(VoteState::deserialize(&data).unwrap().is_initialized());
+    }
+
+    #[test]
+    fn test_initialize_account_over_sized() {
+        let mut data = vec![0; VoteState::size() + 100];
+        assert_eq!(Ok(()), initialize_with_data(&mut data));
+        assert!(VoteState::deserialize(&data[..VoteState::size()])
+            .unwrap()
+            .is_initialized());
+        assert!(data[VoteState::size()..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_initialize_account_too_small() {
+        let mut data = vec![0; VoteState::size() - 1];
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            initialize_with_data(&mut data)
+        );
+    }
+
+    #[test]
+    fn test_initialize_account_already_initialized() {
+        let mut data = vec![0; VoteState::size()];
+        assert_eq!(Ok(()), initialize_with_data(&mut data));
+        assert_eq!(
+            Err(ProgramError::AccountAlreadyInitialized),
+            initialize_with_data(&mut data)
+        );
+
+        let mut data = vec![0; VoteState::size() + 100];
+        assert_eq!(Ok(()), initialize_with_data(&mut data));
+        assert_eq!(
+            Err(ProgramError::AccountAlreadyInitialized),
+            initialize_with_data(&mut data)
+        );
+    }
+
+    #[test]
+    fn test_initialize_account_pool_marker() {
+        let mut data = vec![0; VoteState::size()];
+        assert_eq!(Ok(()), initialize_with_data(&mut data));
+        assert_eq!(None, VoteState::deserialize(&data).unwrap().pool_marker());
+
+        let pool = Pubkey::new_unique();
+        let init_data = InitializeAccountInstructionData {
+            pool_marker: OptionalNonZeroPubkey(pool),
+            ..init_data()
+        };
+        let mut account = TestAccount::with_data(vec![0; VoteState::size()]);
+        assert_eq!(
+            Ok(()),
+            initialize_account(&account.info(), &init_data, None, &Clock::default())
+        );
+        assert_eq!(
+            Some(pool),
+            VoteState::deserialize(&account.data).unwrap().pool_marker()
+        );
+    }
+
+    #[test]
+    fn test_check_node_identity_signer() {
+        let init_data = init_data();
+        let owner = solana_program::system_program::id();
+        let mut lamports = 0;
+        let mut data = vec![];
+
+        for (key, is_signer, expected) in [
+            (init_data.node_pubkey, true, Ok(())),
+            (
+                init_data.node_pubkey,
+                false,
+                Err(VoteError::NodeIdentityMustSign.into()),
+            ),
+            (
+                Pubkey::new_unique(),
+                true,
+                Err(VoteError::NodeIdentityMustSign.into()),
+            ),
+        ] {
+            let node_account = AccountInfo::new(
+                &key,
+                is_signer,
+                false,
+                &mut lamports,
+                &mut data,
+                &owner,
+                false,
+                0,
+            );
+            assert_eq!(
+                expected,
+                check_node_identity_signer(&node_account, &init_data)
+            );
         }
     }
 
-    VoteState::set_vote_account_state(vote_account, &VoteState::new(init_data, clock))
+    #[test]
+    fn test_require_signer() {
+        let key = Pubkey::new_unique();
+        let owner = solana_program::system_program::id();
+        let mut lamports = 0;
+        let mut data = vec![];
+
+        for (is_signer, expected) in [
+            (true, Ok(&key)),
+            (false, Err(ProgramError::MissingRequiredSignature)),
+        ] {
+            let account = AccountInfo::new(
+                &key,
+                is_signer,
+                false,
+                &mut lamports,
+                &mut data,
+                &owner,
+                false,
+                0,
+            );
+            assert_eq!(expected, require_signer(&account));
+        }
+    }
+
+    #[test]
+    fn test_missing_account() {
+        let program_id = crate::id();
+        let keys = [(); 5].map(|_| Pubkey::new_unique());
+        let mut lamports = [0; 5];
+        let mut data = [vec![0; VoteState::size()], vec![], vec![], vec![], vec![]];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, true, true, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+
+        for instruction in (0..=u8::MAX).filter_map(|tag| VoteInstruction::try_from(tag).ok()) {
+            let expected = instruction.expected_account_count();
+            assert!(expected <= accounts.len());
+            assert_eq!(
+                Err(ProgramError::NotEnoughAccountKeys),
+                process_instruction(
+                    &program_id,
+                    &accounts[..expected - 1],
+                    &instruction_data_header(instruction),
+                ),
+                "{instruction:?}"
+            );
+        }
+    }
 }
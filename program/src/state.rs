@@ -1,30 +1,53 @@
 //! Program state
 
 use bytemuck::{Pod, Zeroable};
-use solana_bls_signatures::Pubkey as BlsPubkey;
+use solana_bls_signatures::{Pubkey as BlsPubkey, BLS_PUBLIC_KEY_AFFINE_SIZE};
 use solana_program::account_info::AccountInfo;
 use solana_program::clock::Clock;
 use solana_program::clock::Epoch;
 use solana_program::clock::Slot;
 use solana_program::clock::UnixTimestamp;
-use solana_program::hash::Hash;
+use solana_program::epoch_schedule::EpochSchedule;
+use solana_program::hash::{hashv, Hash};
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
-use spl_pod::primitives::{PodI64, PodU64};
+use spl_pod::optional_keys::OptionalNonZeroPubkey;
+use spl_pod::primitives::{PodBool, PodI64, PodU16, PodU64};
+use std::cell::Ref;
 
-use crate::accounting::{AuthorizedVoter, EpochCredit};
-use crate::instruction::InitializeAccountInstructionData;
+use crate::accounting::{
+    check_commission_update, voter_activation_slot, AuthorizedVoter, EpochCredit,
+};
+use crate::error::VoteError;
+use crate::instruction::{InitializeAccountInstructionData, VoteInstruction};
+use crate::vote::Vote;
 
+#[cfg(all(feature = "vote-interface", not(target_os = "solana")))]
+use solana_vote_interface::state::{
+    VoteInit as LegacyVoteInit, VoteState as LegacyVoteState, VoteStateVersions,
+};
 #[cfg(not(target_os = "solana"))]
 use {
     solana_account::AccountSharedData, solana_account::WritableAccount,
     solana_vote_interface::state::BlockTimestamp as LegacyBlockTimestamp,
 };
 
-pub(crate) type PodEpoch = PodU64;
-pub(crate) type PodSlot = PodU64;
-pub(crate) type PodUnixTimestamp = PodI64;
+/// An `Epoch` as stored in program state and instruction data
+pub type PodEpoch = PodU64;
+
+/// A `Slot` as stored in program state and instruction data
+///
+/// ```
+/// use alpenglow_vote::state::PodSlot;
+///
+/// let slot = PodSlot::from(5u64);
+/// assert_eq!(5, u64::from(slot));
+/// ```
+pub type PodSlot = PodU64;
+
+/// A `UnixTimestamp` as stored in program state
+pub type PodUnixTimestamp = PodI64;
 
 /// The accounting and vote information associated with
 /// this vote account
@@ -63,6 +86,46 @@ pub struct VoteState {
 
     /// Associated BLS public key
     pub(crate) bls_pubkey: BlsPubkey,
+
+    /// The collector for the commission portion of rewards, if any
+    pub(crate) collector_authority: OptionalNonZeroPubkey,
+
+    /// Number of notarization and notarization fallback votes cast
+    pub(crate) notarize_count: PodU64,
+
+    /// Number of finalization votes cast
+    pub(crate) finalize_count: PodU64,
+
+    /// Number of skip and skip fallback votes cast
+    pub(crate) skip_count: PodU64,
+
+    /// Stake pool this account belongs to, if any
+    pub(crate) pool_marker: OptionalNonZeroPubkey,
+
+    /// The highest slot this account has cast a finalization vote for
+    pub(crate) last_finalized_slot: PodSlot,
+
+    /// Whether voting has been paused by the withdraw authority
+    pub(crate) paused: PodBool,
+
+    /// The instruction discriminant of the last vote landed, 0 if none
+    pub(crate) last_vote_kind: u8,
+
+    /// The slot of the last vote landed
+    pub(crate) last_vote_slot: PodSlot,
+
+    /// The block id of the last vote landed, zero for votes without one
+    pub(crate) last_vote_block_id: Hash,
+
+    /// Whether governance has assigned this account a rank
+    pub(crate) rank_assigned: PodBool,
+
+    /// The position of this validator in certificate bitmaps
+    pub(crate) rank: PodU16,
+
+    /// The signed difference between the commission and the one it replaced
+    /// in the last commission update, 0 if there was none
+    pub(crate) last_commission_change: i8,
 }
 
 #[repr(C)]
@@ -95,8 +158,84 @@ impl From<&BlockTimestamp> for LegacyBlockTimestamp {
     }
 }
 
+/// Wrap the vote state as a `Current` interface vote state for tooling built
+/// on `VoteStateVersions`. The tower, root slot and prior voters have no
+/// counterpart here and are left empty; an uninitialized vote state maps to
+/// one reporting `is_uninitialized()`
+#[cfg(all(feature = "vote-interface", not(target_os = "solana")))]
+impl From<&VoteState> for VoteStateVersions {
+    fn from(vote_state: &VoteState) -> Self {
+        if !vote_state.is_initialized() {
+            return Self::new_current(LegacyVoteState::default());
+        }
+
+        let epoch = vote_state.authorized_voter.epoch();
+        let mut legacy = LegacyVoteState::new(
+            &LegacyVoteInit {
+                node_pubkey: vote_state.node_pubkey,
+                authorized_voter: vote_state
+                    .get_authorized_voter(epoch)
+                    .unwrap_or(vote_state.authorized_voter.voter),
+                authorized_withdrawer: vote_state.authorized_withdrawer,
+                commission: vote_state.commission,
+            },
+            &Clock {
+                epoch,
+                ..Clock::default()
+            },
+        );
+        if let Some(next) = vote_state
+            .next_authorized_voter
+            .filter(|next| next.epoch() > epoch)
+        {
+            // Only fails for a target epoch at or before `epoch`, excluded above
+            let _ = legacy.set_new_authorized_voter(&next.voter, epoch, next.epoch(), |_| Ok(()));
+        }
+        legacy.epoch_credits = vote_state
+            .epoch_credits_ordered()
+            .iter()
+            .map(|credits| (credits.epoch(), credits.credits(), credits.prev_credits()))
+            .collect();
+        legacy.last_timestamp = vote_state.latest_timestamp_legacy_format();
+        Self::new_current(legacy)
+    }
+}
+
+/// Cluster-wide commission policy set by governance, stored at the address
+/// given by `instruction::derive_commission_config`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable, Default, PartialEq)]
+pub struct CommissionConfig {
+    /// The highest commission a vote account may set, in percent
+    pub max_commission: u8,
+}
+
 impl VoteState {
-    const VOTE_STATE_VERSION: u8 = 1;
+    /// Instructions that update the vote state first rewrite older versions in
+    /// this layout with `migrate`. The other instructions read older versions
+    /// with `deserialize_versioned`
+    const VOTE_STATE_VERSION: u8 = 9;
+
+    /// Vote state versions that `deserialize_versioned` can read
+    pub const SUPPORTED_VERSIONS: &'static [u8] =
+        &[1, 2, 3, 4, 5, 6, 7, 8, Self::VOTE_STATE_VERSION];
+
+    /// The serialized size of a supported vote state version. Each version
+    /// only appends fields to the previous one
+    pub fn size_for_version(version: u8) -> Option<usize> {
+        match version {
+            1 => Some(std::mem::offset_of!(VoteState, collector_authority)),
+            2 => Some(std::mem::offset_of!(VoteState, notarize_count)),
+            3 => Some(std::mem::offset_of!(VoteState, pool_marker)),
+            4 => Some(std::mem::offset_of!(VoteState, last_finalized_slot)),
+            5 => Some(std::mem::offset_of!(VoteState, paused)),
+            6 => Some(std::mem::offset_of!(VoteState, last_vote_kind)),
+            7 => Some(std::mem::offset_of!(VoteState, rank_assigned)),
+            8 => Some(std::mem::offset_of!(VoteState, last_commission_change)),
+            Self::VOTE_STATE_VERSION => Some(Self::size()),
+            _ => None,
+        }
+    }
 
     pub(crate) fn new(init_data: &InitializeAccountInstructionData, clock: &Clock) -> Self {
         Self {
@@ -110,6 +249,7 @@ impl VoteState {
             authorized_withdrawer: init_data.authorized_withdrawer,
             commission: init_data.commission,
             bls_pubkey: init_data.bls_pubkey,
+            pool_marker: init_data.pool_marker,
             ..VoteState::default()
         }
     }
@@ -165,22 +305,148 @@ impl VoteState {
         self.version > 0
     }
 
+    /// Return whether the vote account data is initialized.
+    /// Only the leading version byte is read, so this is independent of the
+    /// length of the account
+    pub fn is_initialized_data(vote_account_data: &[u8]) -> bool {
+        vote_account_data
+            .first()
+            .is_some_and(|version| *version > 0)
+    }
+
+    /// Whether the vote account data holds an initialized vote state older than
+    /// the current version. Only the leading version byte is read
+    pub fn needs_migration(vote_account_data: &[u8]) -> bool {
+        vote_account_data
+            .first()
+            .is_some_and(|version| *version > 0 && *version < Self::VOTE_STATE_VERSION)
+    }
+
+    /// Write `vote_state` into the prefix of the vote account data, failing if
+    /// the account is too small to hold it
     pub(crate) fn set_vote_account_state(
         vote_account: &AccountInfo,
         vote_state: &VoteState,
     ) -> Result<(), ProgramError> {
         vote_account
             .try_borrow_mut_data()?
+            .get_mut(..Self::size())
+            .ok_or(ProgramError::AccountDataTooSmall)?
             .copy_from_slice(bytemuck::bytes_of(vote_state));
         Ok(())
     }
 
+    /// Zero the vote state of any supported version, leaving the account
+    /// uninitialized. Unlike `set_vote_account_state`, accounts smaller than
+    /// `size()` are accepted
+    pub(crate) fn clear_vote_account_state(vote_account: &AccountInfo) -> Result<(), ProgramError> {
+        let mut data = vote_account.try_borrow_mut_data()?;
+        let len = data.len().min(Self::size());
+        data[..len].fill(0);
+        Ok(())
+    }
+
+    /// Rewrite an older vote state version in the current layout, growing the
+    /// account to `size()` if it is smaller. Fails with `InsufficientFunds` if
+    /// the account would not be rent exempt at the larger size
+    pub(crate) fn migrate(vote_account: &AccountInfo, rent: &Rent) -> Result<(), ProgramError> {
+        let vote_state = Self::deserialize_versioned(&vote_account.try_borrow_data()?)?;
+        if vote_account.data_len() < Self::size() {
+            if !rent.is_exempt(vote_account.lamports(), Self::size()) {
+                return Err(ProgramError::InsufficientFunds);
+            }
+            vote_account.realloc(Self::size(), false)?;
+        }
+        Self::set_vote_account_state(vote_account, &vote_state)
+    }
+
     /// Deserialize a vote state from input data.
     /// Callers can use this with the `data` field from an `AccountInfo`
     pub fn deserialize(vote_account_data: &[u8]) -> Result<&VoteState, ProgramError> {
         spl_pod::bytemuck::pod_from_bytes::<VoteState>(vote_account_data)
     }
 
+    /// Deserialize a vote state from the borrowed data of a vote account.
+    /// Unlike `deserialize`, only the `size()` prefix is read, so accounts
+    /// allocated with extra space are accepted
+    pub fn deserialize_from_account<'a>(
+        data: &'a Ref<&mut [u8]>,
+    ) -> Result<&'a VoteState, ProgramError> {
+        data.get(..Self::size())
+            .ok_or(ProgramError::InvalidAccountData)
+            .and_then(Self::deserialize)
+    }
+
+    /// Mutably borrow the vote state in the `size()` prefix of vote account
+    /// data, failing with `InvalidAccountData` if the account is too small or
+    /// holds an older version that has not been migrated
+    pub(crate) fn deserialize_from_account_mut(
+        data: &mut [u8],
+    ) -> Result<&mut VoteState, ProgramError> {
+        if Self::needs_migration(data) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        data.get_mut(..Self::size())
+            .ok_or(ProgramError::InvalidAccountData)
+            .and_then(spl_pod::bytemuck::pod_from_bytes_mut)
+    }
+
+    /// Borrow the vote state stored in a vote account. Fails with
+    /// `InvalidAccountOwner` if the account is not owned by this program and
+    /// `InvalidAccountData` if it is too small to hold a vote state or holds
+    /// an older vote state version, whose layout differs from `VoteState`
+    pub fn from_account_info<'a>(
+        vote_account: &'a AccountInfo,
+    ) -> Result<Ref<'a, VoteState>, ProgramError> {
+        if *vote_account.owner != crate::id() {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        if vote_account.data_len() < Self::size() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let data = vote_account.try_borrow_data()?;
+        if Self::needs_migration(&data) || data[0] > Self::VOTE_STATE_VERSION {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Ref::map(data, |data| {
+            bytemuck::from_bytes(&data[..Self::size()])
+        }))
+    }
+
+    /// Deserialize a vote state by first reading its version byte and then
+    /// reading the layout of that version, upgrading older layouts to the
+    /// current in-memory representation. Fields added after the stored
+    /// version are zero
+    pub fn deserialize_versioned(vote_account_data: &[u8]) -> Result<VoteState, ProgramError> {
+        let version = *vote_account_data
+            .first()
+            .ok_or(ProgramError::InvalidAccountData)?;
+        match version {
+            Self::VOTE_STATE_VERSION => vote_account_data
+                .get(..Self::size())
+                .map(bytemuck::pod_read_unaligned)
+                .ok_or(ProgramError::InvalidAccountData),
+            version => Self::upgrade_appended_layout(version, vote_account_data),
+        }
+    }
+
+    /// Read an older version whose layout is a prefix of the current one,
+    /// zeroing the fields appended since
+    fn upgrade_appended_layout(
+        version: u8,
+        vote_account_data: &[u8],
+    ) -> Result<VoteState, ProgramError> {
+        let size = Self::size_for_version(version).ok_or(ProgramError::InvalidAccountData)?;
+        let data = vote_account_data
+            .get(..size)
+            .ok_or(ProgramError::InvalidAccountData)?;
+
+        let mut vote_state = VoteState::default();
+        bytemuck::bytes_of_mut(&mut vote_state)[..size].copy_from_slice(data);
+        vote_state.version = Self::VOTE_STATE_VERSION;
+        Ok(vote_state)
+    }
+
     /// Serializes a vote state into an output buffer
     /// Callers can use this with the mutable reference to `data` from
     /// an `AccountInfo`
@@ -194,16 +460,67 @@ impl VoteState {
         std::mem::size_of::<VoteState>()
     }
 
+    /// Commitment over the serialized vote state for light client proofs.
+    /// Covers every field, including the version, vote counters and replay
+    /// information
+    pub fn state_hash(&self) -> Hash {
+        hashv(&[bytemuck::bytes_of(self)])
+    }
+
     /// Vote state version
     pub fn version(&self) -> u8 {
         self.version
     }
 
+    /// Whether two vote states have the same identity, authorities, commission
+    /// and credits. The version, vote counters, last finalized slot, last vote,
+    /// last commission change and replay information are ignored
+    pub fn semantically_eq(&self, other: &VoteState) -> bool {
+        self.node_pubkey == other.node_pubkey
+            && self.bls_pubkey == other.bls_pubkey
+            && self.authorized_withdrawer == other.authorized_withdrawer
+            && self.authorized_voter == other.authorized_voter
+            && self.next_authorized_voter == other.next_authorized_voter
+            && self.collector_authority == other.collector_authority
+            && self.pool_marker == other.pool_marker
+            && self.paused == other.paused
+            && self.rank() == other.rank()
+            && self.commission == other.commission
+            && self.epoch_credits == other.epoch_credits
+    }
+
+    /// Check the invariants of an initialized vote state: a non-zero version,
+    /// a commission of at most 100 percent, a non-zero next authorized voter
+    /// epoch and a zero rank unless one has been assigned
+    pub fn check_invariants(&self) -> Result<(), VoteError> {
+        let holds = self.version != 0
+            && self.commission <= 100
+            && self
+                .next_authorized_voter
+                .is_none_or(|next_authorized_voter| next_authorized_voter.epoch() != 0)
+            && (bool::from(self.rank_assigned) || u16::from(self.rank) == 0);
+        if !holds {
+            return Err(VoteError::InvalidVoteState);
+        }
+        Ok(())
+    }
+
+    /// Whether this vote state was written by the current program version
+    pub fn is_current_version(&self) -> bool {
+        self.version == Self::VOTE_STATE_VERSION
+    }
+
     /// Validator that votes in this account
     pub fn node_pubkey(&self) -> &Pubkey {
         &self.node_pubkey
     }
 
+    /// Whether the account has a node identity, rather than the default
+    /// pubkey placeholder of a commission-only account, which cannot vote
+    pub fn has_node_identity(&self) -> bool {
+        self.node_pubkey != Pubkey::default()
+    }
+
     /// Signer for withdrawals
     pub fn authorized_withdrawer(&self) -> &Pubkey {
         &self.authorized_withdrawer
@@ -215,6 +532,12 @@ impl VoteState {
         self.commission
     }
 
+    /// The signed change of the last commission update, negative for a
+    /// decrease, 0 if the commission has not been updated
+    pub fn last_commission_change(&self) -> i8 {
+        self.last_commission_change
+    }
+
     /// The authorized voter for the given epoch
     pub fn get_authorized_voter(&self, epoch: Epoch) -> Option<Pubkey> {
         if let Some(av) = self.next_authorized_voter {
@@ -228,11 +551,27 @@ impl VoteState {
         None
     }
 
+    /// The key that must sign a vote landing at `clock`: the next authorized
+    /// voter from its epoch on, the current one before. Votes are checked
+    /// against this key, so clients should sign with it
+    pub fn signer_for_vote(&self, clock: &Clock) -> Option<Pubkey> {
+        self.get_authorized_voter(clock.epoch)
+    }
+
     /// Get rent exempt reserve
     pub fn get_rent_exempt_reserve(rent: &Rent) -> u64 {
         rent.minimum_balance(Self::size())
     }
 
+    /// Lamports that can be withdrawn from an account of `data_len` bytes holding
+    /// `account_lamports` while keeping it rent exempt. The rent floor is that of
+    /// the whole account, as withdraw requires, so over-allocated accounts keep
+    /// more. Closing the account, which withdraws the full balance, is not
+    /// accounted for here
+    pub fn max_withdraw(account_lamports: u64, data_len: usize, rent: &Rent) -> u64 {
+        account_lamports.saturating_sub(rent.minimum_balance(data_len))
+    }
+
     /// The signer for vote transactions in this epoch
     pub fn authorized_voter(&self) -> &AuthorizedVoter {
         &self.authorized_voter
@@ -243,11 +582,119 @@ impl VoteState {
         self.next_authorized_voter.as_ref()
     }
 
+    /// The first slot at which the next authorized voter can sign votes, if one is set
+    pub fn next_voter_activation_slot(&self, epoch_schedule: &EpochSchedule) -> Option<Slot> {
+        self.next_authorized_voter().map(|next_authorized_voter| {
+            voter_activation_slot(next_authorized_voter.epoch(), epoch_schedule)
+        })
+    }
+
+    /// Whether an authorized voter change is queued to take effect after `current_epoch`
+    pub fn has_pending_voter_change(&self, current_epoch: Epoch) -> bool {
+        self.next_authorized_voter
+            .is_some_and(|next_authorized_voter| next_authorized_voter.epoch() > current_epoch)
+    }
+
+    /// The authorized voters of `start..=end` as inclusive `(first, last, voter)` epoch
+    /// ranges clipped to the query, following `get_authorized_voter`. Only the current
+    /// and next voters are stored, so the voters of epochs before `current_epoch` are
+    /// unknown and `None` is returned if `start` precedes it. Voters of later epochs are
+    /// reported as currently scheduled, which a later authorize may still change
+    pub fn authorized_voters_in_range(
+        &self,
+        current_epoch: Epoch,
+        start: Epoch,
+        end: Epoch,
+    ) -> Option<Vec<(Epoch, Epoch, Pubkey)>> {
+        if start < current_epoch {
+            return None;
+        }
+        // The current voter is superseded once the next one takes effect
+        let current_last = match self.next_authorized_voter {
+            Some(next) => next.epoch().checked_sub(1),
+            None => Some(Epoch::MAX),
+        };
+        let ranges = [
+            current_last.map(|last| {
+                (
+                    self.authorized_voter.epoch(),
+                    last,
+                    self.authorized_voter.voter,
+                )
+            }),
+            self.next_authorized_voter
+                .map(|next| (next.epoch(), Epoch::MAX, next.voter)),
+        ];
+        Some(
+            ranges
+                .into_iter()
+                .flatten()
+                .filter_map(|(first, last, voter)| {
+                    let first = first.max(start);
+                    let last = last.min(end);
+                    (first <= last).then_some((first, last, voter))
+                })
+                .collect(),
+        )
+    }
+
     /// How many credits this validator is earning in this Epoch
     pub fn epoch_credits(&self) -> &EpochCredit {
         &self.epoch_credits
     }
 
+    /// The epoch of the stored `EpochCredit`, i.e. the last epoch in which this
+    /// account earned credits. This is not the current clock epoch
+    pub fn last_credited_epoch(&self) -> Epoch {
+        self.epoch_credits.epoch()
+    }
+
+    /// Credits earned between the `prior` snapshot of this account and now.
+    /// On an epoch rollover the credits of the prior epoch are folded into
+    /// `prev_credits`, which is excluded from the earned amount. Only the latest
+    /// epoch is retained, so the snapshots may be at most one rollover apart
+    pub fn credits_earned_since(&self, prior: &VoteState) -> u64 {
+        let current = &self.epoch_credits;
+        let prior = &prior.epoch_credits;
+        if current.epoch() == prior.epoch() {
+            current.credits().saturating_sub(prior.credits())
+        } else {
+            current
+                .credits()
+                .saturating_sub(prior.prev_credits())
+                .saturating_sub(prior.credits())
+        }
+    }
+
+    /// Credits earned in the current epoch, `credits - prev_credits`, scaled by
+    /// `fraction_num / fraction_den` for rewards over a partial epoch. Returns 0
+    /// if `fraction_den` is 0
+    pub fn partial_epoch_credits(&self, fraction_num: u64, fraction_den: u64) -> u64 {
+        let earned = self
+            .epoch_credits
+            .credits()
+            .saturating_sub(self.epoch_credits.prev_credits());
+        u128::from(earned)
+            .saturating_mul(u128::from(fraction_num))
+            .checked_div(u128::from(fraction_den))
+            .map_or(0, |credits| u64::try_from(credits).unwrap_or(u64::MAX))
+    }
+
+    /// Epoch credits history ordered oldest to newest, suitable for hashing.
+    /// Only the most recent epoch is retained on chain, so this holds at most
+    /// one entry and is empty if no credits have been recorded
+    pub fn epoch_credits_ordered(&self) -> Vec<EpochCredit> {
+        self.epoch_credits_iter().copied().collect()
+    }
+
+    /// Iterate the epoch credits history oldest to newest without copying it,
+    /// skipping unused entries. As with `epoch_credits_ordered`, only the most
+    /// recent epoch is retained, so this yields at most one entry
+    pub fn epoch_credits_iter(&self) -> impl Iterator<Item = &EpochCredit> {
+        std::iter::once(&self.epoch_credits)
+            .filter(|epoch_credits| **epoch_credits != EpochCredit::default())
+    }
+
     /// Most recent timestamp submitted with a vote
     #[cfg(not(target_os = "solana"))]
     pub fn latest_timestamp_legacy_format(&self) -> LegacyBlockTimestamp {
@@ -265,19 +712,65 @@ impl VoteState {
         self.authorized_withdrawer = authorized_withdrawer
     }
 
-    /// Set the commission
-    pub fn set_commission(&mut self, commission: u8) {
+    /// Set the commission without any validation, see `try_set_commission`
+    pub fn set_commission_unchecked(&mut self, commission: u8) {
         self.commission = commission
     }
 
+    /// Set the commission with the checks of `UpdateCommission`: it may be at
+    /// most 100 percent and at most `max_commission`, and may only be lowered
+    /// or kept in the first half of the epoch of `slot`
+    pub fn try_set_commission(
+        &mut self,
+        commission: u8,
+        max_commission: Option<u8>,
+        slot: Slot,
+        epoch_schedule: &EpochSchedule,
+    ) -> Result<(), VoteError> {
+        check_commission_update(self, commission, max_commission, slot, || {
+            Ok(epoch_schedule.clone())
+        })?;
+        self.record_commission_change(commission);
+        Ok(())
+    }
+
+    /// Set the commission, recording its signed difference from the current
+    /// one as the last commission change
+    pub(crate) fn record_commission_change(&mut self, commission: u8) {
+        let change = i16::from(commission) - i16::from(self.commission);
+        self.last_commission_change =
+            i8::try_from(change).unwrap_or(if change < 0 { i8::MIN } else { i8::MAX });
+        self.commission = commission;
+    }
+
     /// Set the authorized voter
     pub fn set_authorized_voter(&mut self, authorized_voter: AuthorizedVoter) {
         self.authorized_voter = authorized_voter
     }
 
-    /// Set the next authorized voter
-    pub fn set_next_authorized_voter(&mut self, next_authorized_voter: AuthorizedVoter) {
-        self.next_authorized_voter = Some(next_authorized_voter)
+    /// Make the next authorized voter the current one once its epoch has been
+    /// reached, so that scheduling another voter cannot hand the epochs before
+    /// that voter back to the superseded one
+    pub(crate) fn promote_next_authorized_voter(&mut self, current_epoch: Epoch) {
+        if let Some(next_authorized_voter) = self.next_authorized_voter {
+            if current_epoch >= next_authorized_voter.epoch() {
+                self.authorized_voter = next_authorized_voter;
+                self.next_authorized_voter = None;
+            }
+        }
+    }
+
+    /// Set the next authorized voter. Its epoch must be non-zero, as a zero
+    /// epoch is indistinguishable from `None` in account data
+    pub fn set_next_authorized_voter(
+        &mut self,
+        next_authorized_voter: AuthorizedVoter,
+    ) -> Result<(), VoteError> {
+        if next_authorized_voter.epoch() == 0 {
+            return Err(VoteError::ZeroNextVoterEpoch);
+        }
+        self.next_authorized_voter = Some(next_authorized_voter);
+        Ok(())
     }
 
     /// Set the epoch credits
@@ -289,4 +782,1297 @@ impl VoteState {
     pub fn bls_pubkey(&self) -> &BlsPubkey {
         &self.bls_pubkey
     }
+
+    /// Associated BLS public key as the affine bytes used on the wire
+    pub fn bls_pubkey_affine(&self) -> [u8; BLS_PUBLIC_KEY_AFFINE_SIZE] {
+        self.bls_pubkey.0
+    }
+
+    /// The collector for the commission portion of rewards, if any
+    pub fn collector_authority(&self) -> Option<Pubkey> {
+        Option::<Pubkey>::from(self.collector_authority)
+    }
+
+    /// Stake pool this account belongs to, if any
+    pub fn pool_marker(&self) -> Option<Pubkey> {
+        Option::<Pubkey>::from(self.pool_marker)
+    }
+
+    /// The highest slot this account has cast a finalization vote for
+    pub fn last_finalized_slot(&self) -> Slot {
+        Slot::from(self.last_finalized_slot)
+    }
+
+    /// Whether voting has been paused by the withdraw authority
+    pub fn paused(&self) -> bool {
+        bool::from(self.paused)
+    }
+
+    /// The rank assigned by governance, which indexes this validator's
+    /// signature in certificate bitmaps, if any
+    pub fn rank(&self) -> Option<u16> {
+        bool::from(self.rank_assigned).then(|| u16::from(self.rank))
+    }
+
+    /// Whether `vote` is the last vote landed by this account, so that a
+    /// client retrying it can treat the retry as a success. Votes are compared
+    /// by kind, slot and block id
+    pub fn matches_last_vote(&self, vote: &Vote) -> bool {
+        self.last_vote_kind != 0
+            && self.last_vote_kind == last_vote_kind(vote)
+            && Slot::from(self.last_vote_slot) == vote.slot()
+            && self.last_vote_block_id == vote.block_id().copied().unwrap_or_default()
+    }
+
+    /// Record a vote cast by a `kind` instruction as the last vote landed by
+    /// this account
+    pub(crate) fn set_last_vote(&mut self, kind: VoteInstruction, slot: Slot, block_id: Hash) {
+        self.last_vote_kind = u8::from(kind);
+        self.last_vote_slot = PodSlot::from(slot);
+        self.last_vote_block_id = block_id;
+    }
+
+    /// Number of notarization and notarization fallback votes cast
+    pub fn notarize_count(&self) -> u64 {
+        u64::from(self.notarize_count)
+    }
+
+    /// Number of finalization votes cast
+    pub fn finalize_count(&self) -> u64 {
+        u64::from(self.finalize_count)
+    }
+
+    /// Number of skip and skip fallback votes cast
+    pub fn skip_count(&self) -> u64 {
+        u64::from(self.skip_count)
+    }
+
+    /// Set the collector for the commission portion of rewards
+    pub fn set_collector_authority(&mut self, collector_authority: Option<Pubkey>) {
+        self.collector_authority = OptionalNonZeroPubkey(collector_authority.unwrap_or_default())
+    }
+
+    /// Pause or resume voting
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = PodBool::from(paused)
+    }
+}
+
+/// The discriminant of the instruction that casts `vote`
+fn last_vote_kind(vote: &Vote) -> u8 {
+    u8::from(match vote {
+        Vote::Notarize(_) => VoteInstruction::Notarize,
+        Vote::Finalize(_) => VoteInstruction::Finalize,
+        Vote::Skip(_) => VoteInstruction::Skip,
+        Vote::NotarizeFallback(_) => VoteInstruction::NotarizeFallback,
+        Vote::SkipFallback(_) => VoteInstruction::SkipFallback,
+    })
+}
+
+/// Builds an initialized `VoteState` for tests in downstream crates. Fields
+/// that are not set are left zeroed
+#[cfg(any(test, feature = "dev-utils"))]
+#[derive(Clone, Copy, Debug)]
+pub struct VoteStateBuilder {
+    vote_state: VoteState,
+}
+
+#[cfg(any(test, feature = "dev-utils"))]
+impl Default for VoteStateBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "dev-utils"))]
+impl VoteStateBuilder {
+    /// Create a builder for a vote state at the current version
+    pub fn new() -> Self {
+        Self {
+            vote_state: VoteState {
+                version: VoteState::VOTE_STATE_VERSION,
+                ..VoteState::default()
+            },
+        }
+    }
+
+    /// Set the validator identity
+    pub fn node_pubkey(mut self, node_pubkey: Pubkey) -> Self {
+        self.vote_state.node_pubkey = node_pubkey;
+        self
+    }
+
+    /// Set the voter authorized from `epoch`
+    pub fn authorized_voter(mut self, authorized_voter: Pubkey, epoch: Epoch) -> Self {
+        self.vote_state.authorized_voter = AuthorizedVoter::new(epoch, authorized_voter);
+        self
+    }
+
+    /// Set the withdraw authority
+    pub fn authorized_withdrawer(mut self, authorized_withdrawer: Pubkey) -> Self {
+        self.vote_state.authorized_withdrawer = authorized_withdrawer;
+        self
+    }
+
+    /// Set the commission percentage
+    pub fn commission(mut self, commission: u8) -> Self {
+        self.vote_state.commission = commission;
+        self
+    }
+
+    /// Set the BLS public key
+    pub fn bls_pubkey(mut self, bls_pubkey: BlsPubkey) -> Self {
+        self.vote_state.bls_pubkey = bls_pubkey;
+        self
+    }
+
+    /// Set the epoch credits
+    pub fn epoch_credits(mut self, epoch_credits: EpochCredit) -> Self {
+        self.vote_state.epoch_credits = epoch_credits;
+        self
+    }
+
+    /// Set the commission collector
+    pub fn collector_authority(mut self, collector_authority: Option<Pubkey>) -> Self {
+        self.vote_state.set_collector_authority(collector_authority);
+        self
+    }
+
+    /// Produce the vote state
+    pub fn build(self) -> VoteState {
+        self.vote_state
+    }
+}
+
+/// Byte offsets of the `VoteState` fields in account data, for clients that
+/// read single fields without deserializing the whole account
+pub mod offsets {
+    use std::mem::{offset_of, size_of};
+
+    use crate::accounting::EpochCredit;
+    use crate::state::VoteState;
+
+    /// The vote state version
+    pub const VERSION_OFFSET: usize = offset_of!(VoteState, version);
+    /// The node that votes in this account
+    pub const NODE_PUBKEY_OFFSET: usize = offset_of!(VoteState, node_pubkey);
+    /// The signer for withdrawals
+    pub const AUTHORIZED_WITHDRAWER_OFFSET: usize = offset_of!(VoteState, authorized_withdrawer);
+    /// The commission percentage
+    pub const COMMISSION_OFFSET: usize = offset_of!(VoteState, commission);
+    /// The signer for vote transactions in this epoch
+    pub const AUTHORIZED_VOTER_OFFSET: usize = offset_of!(VoteState, authorized_voter);
+    /// The signer for vote transactions in an upcoming epoch
+    pub const NEXT_AUTHORIZED_VOTER_OFFSET: usize = offset_of!(VoteState, next_authorized_voter);
+    /// The `EpochCredit` of the current epoch
+    pub const EPOCH_CREDITS_OFFSET: usize = offset_of!(VoteState, epoch_credits);
+    /// The credits earned in the current epoch, inside `EpochCredit`
+    pub const CREDITS_OFFSET: usize = EPOCH_CREDITS_OFFSET + offset_of!(EpochCredit, credits);
+    /// The BLS public key
+    pub const BLS_PUBKEY_OFFSET: usize = offset_of!(VoteState, bls_pubkey);
+    /// The commission collector, zero if none
+    pub const COLLECTOR_AUTHORITY_OFFSET: usize = offset_of!(VoteState, collector_authority);
+    /// The notarization vote count
+    pub const NOTARIZE_COUNT_OFFSET: usize = offset_of!(VoteState, notarize_count);
+    /// The finalization vote count
+    pub const FINALIZE_COUNT_OFFSET: usize = offset_of!(VoteState, finalize_count);
+    /// The skip vote count
+    pub const SKIP_COUNT_OFFSET: usize = offset_of!(VoteState, skip_count);
+    /// The stake pool marker, zero if none
+    pub const POOL_MARKER_OFFSET: usize = offset_of!(VoteState, pool_marker);
+    /// The highest finalized slot voted on
+    pub const LAST_FINALIZED_SLOT_OFFSET: usize = offset_of!(VoteState, last_finalized_slot);
+    /// The paused flag
+    pub const PAUSED_OFFSET: usize = offset_of!(VoteState, paused);
+    /// The discriminant of the last vote
+    pub const LAST_VOTE_KIND_OFFSET: usize = offset_of!(VoteState, last_vote_kind);
+    /// The slot of the last vote
+    pub const LAST_VOTE_SLOT_OFFSET: usize = offset_of!(VoteState, last_vote_slot);
+    /// The block id of the last vote
+    pub const LAST_VOTE_BLOCK_ID_OFFSET: usize = offset_of!(VoteState, last_vote_block_id);
+    /// The rank assigned flag
+    pub const RANK_ASSIGNED_OFFSET: usize = offset_of!(VoteState, rank_assigned);
+    /// The governance-assigned rank
+    pub const RANK_OFFSET: usize = offset_of!(VoteState, rank);
+    /// The signed change of the last commission update
+    pub const LAST_COMMISSION_CHANGE_OFFSET: usize = offset_of!(VoteState, last_commission_change);
+
+    // A field appended to `VoteState` needs an offset here
+    const _: () =
+        assert!(LAST_COMMISSION_CHANGE_OFFSET + size_of::<i8>() == size_of::<VoteState>());
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_bls_signatures::Pubkey as BlsPubkey;
+    use solana_program::clock::{Clock, Epoch};
+    use solana_program::epoch_schedule::EpochSchedule;
+    use solana_program::hash::Hash;
+    use solana_program::program_error::ProgramError;
+    use solana_program::pubkey::Pubkey;
+    use solana_program::rent::Rent;
+    use spl_pod::primitives::{PodU16, PodU64};
+
+    use crate::accounting;
+    use crate::accounting::{AuthorizedVoter, EpochCredit};
+    use crate::error::VoteError;
+    use crate::instruction::{AuthorityType, VoteInstruction};
+    use crate::state::{PodSlot, VoteState};
+    use crate::test_utils::TestAccount;
+    use crate::vote::Vote;
+
+    #[test]
+    fn test_has_pending_voter_change() {
+        let authorized_voter = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            authorized_voter,
+            10,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert!(!vote_state.has_pending_voter_change(10));
+        assert_eq!(
+            None,
+            vote_state.next_voter_activation_slot(&EpochSchedule::default())
+        );
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let clock = Clock {
+            epoch: 10,
+            leader_schedule_epoch: 11,
+            ..Clock::default()
+        };
+        accounting::authorize(
+            &vote_account,
+            &Pubkey::new_unique(),
+            AuthorityType::Voter,
+            &authorized_voter,
+            false,
+            &clock,
+        )
+        .unwrap();
+
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(12, vote_state.next_authorized_voter().unwrap().epoch());
+
+        // Right after the authorize the change is pending
+        assert!(vote_state.has_pending_voter_change(10));
+        assert!(vote_state.has_pending_voter_change(11));
+        // Once the activation epoch is reached it no longer is
+        assert!(!vote_state.has_pending_voter_change(12));
+        assert!(!vote_state.has_pending_voter_change(13));
+
+        let epoch_schedule = EpochSchedule::default();
+        assert_eq!(
+            Some(epoch_schedule.get_first_slot_in_epoch(12)),
+            vote_state.next_voter_activation_slot(&epoch_schedule)
+        );
+    }
+
+    #[test]
+    fn test_authorized_voters_in_range() {
+        let voter_0 = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            voter_0,
+            10,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert_eq!(
+            Some(vec![(10, 20, voter_0)]),
+            vote_state.authorized_voters_in_range(10, 10, 20)
+        );
+        // Voters before the current epoch are not stored
+        assert_eq!(None, vote_state.authorized_voters_in_range(10, 9, 20));
+        assert_eq!(None, vote_state.authorized_voters_in_range(11, 10, 20));
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+
+        // Reauthorize in epochs 10, 13 and 16; each takes effect two epochs later
+        // and is signed by the voter in effect, which keeps voting until then
+        let voters = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut current_voter = voter_0;
+        for (epoch, voter) in [10, 13, 16].into_iter().zip(voters) {
+            let clock = Clock {
+                epoch,
+                leader_schedule_epoch: epoch + 1,
+                ..Clock::default()
+            };
+            accounting::authorize(
+                &vote_account,
+                &voter,
+                AuthorityType::Voter,
+                &current_voter,
+                false,
+                &clock,
+            )
+            .unwrap();
+
+            let data = vote_account.try_borrow_data().unwrap();
+            let vote_state = VoteState::deserialize(&data).unwrap();
+            assert_eq!(
+                Some(vec![
+                    (epoch, epoch + 1, current_voter),
+                    (epoch + 2, 30, voter)
+                ]),
+                vote_state.authorized_voters_in_range(epoch, epoch, 30)
+            );
+            assert_eq!(
+                None,
+                vote_state.authorized_voters_in_range(epoch, epoch - 1, 30)
+            );
+            current_voter = voter;
+        }
+
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        // Ranges are clipped to the query
+        assert_eq!(
+            Some(vec![(16, 17, voters[1]), (18, 19, voters[2])]),
+            vote_state.authorized_voters_in_range(16, 16, 19)
+        );
+        assert_eq!(
+            Some(vec![(18, Epoch::MAX, voters[2])]),
+            vote_state.authorized_voters_in_range(16, 18, Epoch::MAX)
+        );
+        assert_eq!(
+            Some(vec![(17, 17, voters[1])]),
+            vote_state.authorized_voters_in_range(16, 17, 17)
+        );
+        assert_eq!(
+            Some(vec![]),
+            vote_state.authorized_voters_in_range(16, 20, 19)
+        );
+        for epoch in 16..=30 {
+            assert_eq!(
+                vote_state.get_authorized_voter(epoch),
+                vote_state
+                    .authorized_voters_in_range(16, epoch, epoch)
+                    .unwrap()
+                    .first()
+                    .map(|(_, _, voter)| *voter)
+            );
+        }
+    }
+
+    #[test]
+    fn test_reauthorize_after_voter_change() {
+        let voter_0 = Pubkey::new_unique();
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            voter_0,
+            10,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let clock_at = |epoch| Clock {
+            epoch,
+            leader_schedule_epoch: epoch + 1,
+            ..Clock::default()
+        };
+
+        // Schedule voter_1 for epoch 12
+        let voter_1 = Pubkey::new_unique();
+        accounting::authorize(
+            &vote_account,
+            &voter_1,
+            AuthorityType::Voter,
+            &voter_0,
+            false,
+            &clock_at(10),
+        )
+        .unwrap();
+
+        // Once voter_1 is in effect, voter_0 can no longer reauthorize
+        let voter_2 = Pubkey::new_unique();
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            accounting::authorize(
+                &vote_account,
+                &voter_2,
+                AuthorityType::Voter,
+                &voter_0,
+                false,
+                &clock_at(20),
+            )
+        );
+
+        // voter_1 can, and stays in effect until voter_2 takes over
+        accounting::authorize(
+            &vote_account,
+            &voter_2,
+            AuthorityType::Voter,
+            &voter_1,
+            false,
+            &clock_at(20),
+        )
+        .unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(&voter_1, vote_state.authorized_voter().voter());
+        assert_eq!(
+            Some(vec![(20, 21, voter_1), (22, 30, voter_2)]),
+            vote_state.authorized_voters_in_range(20, 20, 30)
+        );
+    }
+
+    #[test]
+    fn test_offsets() {
+        use crate::state::offsets;
+
+        fn read<T: bytemuck::Pod>(data: &[u8], offset: usize) -> T {
+            bytemuck::pod_read_unaligned(&data[offset..offset + std::mem::size_of::<T>()])
+        }
+
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            7,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        vote_state.set_epoch_credits(EpochCredit::new(9, 1_000, 400));
+        vote_state.set_collector_authority(Some(Pubkey::new_unique()));
+        vote_state.notarize_count = PodU64::from(3);
+        vote_state.finalize_count = PodU64::from(4);
+        vote_state.skip_count = PodU64::from(5);
+        vote_state.last_finalized_slot = PodU64::from(600);
+        vote_state.set_paused(true);
+        vote_state.set_last_vote(VoteInstruction::Skip, 601, Hash::default());
+        vote_state.rank_assigned = true.into();
+        vote_state.rank = 12.into();
+        vote_state.last_commission_change = -3;
+
+        let data = bytemuck::bytes_of(&vote_state);
+        assert_eq!(
+            vote_state.version(),
+            read::<u8>(data, offsets::VERSION_OFFSET)
+        );
+        assert_eq!(
+            *vote_state.node_pubkey(),
+            read::<Pubkey>(data, offsets::NODE_PUBKEY_OFFSET)
+        );
+        assert_eq!(
+            *vote_state.authorized_withdrawer(),
+            read::<Pubkey>(data, offsets::AUTHORIZED_WITHDRAWER_OFFSET)
+        );
+        assert_eq!(42, read::<u8>(data, offsets::COMMISSION_OFFSET));
+        assert_eq!(
+            *vote_state.authorized_voter(),
+            read(data, offsets::AUTHORIZED_VOTER_OFFSET)
+        );
+        assert_eq!(
+            *vote_state.epoch_credits(),
+            read(data, offsets::EPOCH_CREDITS_OFFSET)
+        );
+        assert_eq!(
+            1_000,
+            u64::from(read::<PodU64>(data, offsets::CREDITS_OFFSET))
+        );
+        assert_eq!(
+            *vote_state.bls_pubkey(),
+            read(data, offsets::BLS_PUBKEY_OFFSET)
+        );
+        assert_eq!(
+            vote_state.collector_authority(),
+            Some(read::<Pubkey>(data, offsets::COLLECTOR_AUTHORITY_OFFSET))
+        );
+        assert_eq!(
+            3,
+            u64::from(read::<PodU64>(data, offsets::NOTARIZE_COUNT_OFFSET))
+        );
+        assert_eq!(
+            4,
+            u64::from(read::<PodU64>(data, offsets::FINALIZE_COUNT_OFFSET))
+        );
+        assert_eq!(
+            5,
+            u64::from(read::<PodU64>(data, offsets::SKIP_COUNT_OFFSET))
+        );
+        assert_eq!(
+            Pubkey::default(),
+            read::<Pubkey>(data, offsets::POOL_MARKER_OFFSET)
+        );
+        assert_eq!(
+            600,
+            u64::from(read::<PodU64>(data, offsets::LAST_FINALIZED_SLOT_OFFSET))
+        );
+        assert_eq!(1, read::<u8>(data, offsets::PAUSED_OFFSET));
+        assert_eq!(
+            vote_state.last_vote_kind,
+            read::<u8>(data, offsets::LAST_VOTE_KIND_OFFSET)
+        );
+        assert_eq!(
+            601,
+            u64::from(read::<PodU64>(data, offsets::LAST_VOTE_SLOT_OFFSET))
+        );
+        assert_eq!(
+            Hash::default(),
+            read::<Hash>(data, offsets::LAST_VOTE_BLOCK_ID_OFFSET)
+        );
+        assert_eq!(1, read::<u8>(data, offsets::RANK_ASSIGNED_OFFSET));
+        assert_eq!(
+            vote_state.rank(),
+            Some(u16::from(read::<PodU16>(data, offsets::RANK_OFFSET)))
+        );
+        assert_eq!(-3, read::<i8>(data, offsets::LAST_COMMISSION_CHANGE_OFFSET));
+    }
+
+    #[test]
+    fn test_signer_for_vote() {
+        let voter = Pubkey::new_unique();
+        let next_voter = Pubkey::new_unique();
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            voter,
+            10,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let clock = |epoch| Clock {
+            epoch,
+            ..Clock::default()
+        };
+        assert_eq!(Some(voter), vote_state.signer_for_vote(&clock(10)));
+        assert_eq!(Some(voter), vote_state.signer_for_vote(&clock(12)));
+
+        // The next voter signs from the first slot of its epoch
+        vote_state
+            .set_next_authorized_voter(AuthorizedVoter::new(12, next_voter))
+            .unwrap();
+        assert_eq!(Some(voter), vote_state.signer_for_vote(&clock(11)));
+        assert_eq!(Some(next_voter), vote_state.signer_for_vote(&clock(12)));
+        assert_eq!(Some(next_voter), vote_state.signer_for_vote(&clock(13)));
+
+        // No one signs before the account's first voter
+        assert_eq!(None, vote_state.signer_for_vote(&clock(9)));
+    }
+
+    #[test]
+    fn test_set_next_authorized_voter_zero_epoch() {
+        let mut vote_state = VoteState::default();
+        let voter = Pubkey::new_unique();
+
+        assert_eq!(
+            Err(VoteError::ZeroNextVoterEpoch),
+            vote_state.set_next_authorized_voter(AuthorizedVoter::new(0, voter))
+        );
+        assert_eq!(None, vote_state.next_authorized_voter());
+
+        assert_eq!(
+            Ok(()),
+            vote_state.set_next_authorized_voter(AuthorizedVoter::new(1, voter))
+        );
+        assert_eq!(
+            Some(&AuthorizedVoter::new(1, voter)),
+            vote_state.next_authorized_voter()
+        );
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            10,
+            Pubkey::new_unique(),
+            100,
+            BlsPubkey::default(),
+        );
+        assert_eq!(Ok(()), vote_state.check_invariants());
+
+        let mut uninitialized = vote_state;
+        uninitialized.version = 0;
+        assert_eq!(
+            Err(VoteError::InvalidVoteState),
+            uninitialized.check_invariants()
+        );
+
+        let mut over_commission = vote_state;
+        over_commission.commission = 101;
+        assert_eq!(
+            Err(VoteError::InvalidVoteState),
+            over_commission.check_invariants()
+        );
+
+        let mut zero_epoch_voter = vote_state;
+        zero_epoch_voter.next_authorized_voter =
+            Some(AuthorizedVoter::new(0, Pubkey::new_unique()));
+        assert_eq!(
+            Err(VoteError::InvalidVoteState),
+            zero_epoch_voter.check_invariants()
+        );
+
+        let mut unassigned_rank = vote_state;
+        unassigned_rank.rank = PodU16::from(3);
+        assert_eq!(
+            Err(VoteError::InvalidVoteState),
+            unassigned_rank.check_invariants()
+        );
+        unassigned_rank.rank_assigned = true.into();
+        assert_eq!(Ok(()), unassigned_rank.check_invariants());
+    }
+
+    #[cfg(feature = "vote-interface")]
+    #[test]
+    fn test_into_vote_state_versions() {
+        use solana_vote_interface::state::VoteStateVersions;
+
+        assert!(VoteStateVersions::from(&VoteState::default()).is_uninitialized());
+
+        let node_pubkey = Pubkey::new_unique();
+        let authorized_voter = Pubkey::new_unique();
+        let authorized_withdrawer = Pubkey::new_unique();
+        let mut vote_state = VoteState::new_for_tests(
+            node_pubkey,
+            authorized_voter,
+            10,
+            authorized_withdrawer,
+            42,
+            BlsPubkey::default(),
+        );
+        let next_voter = Pubkey::new_unique();
+        vote_state
+            .set_next_authorized_voter(AuthorizedVoter::new(12, next_voter))
+            .unwrap();
+        vote_state.set_epoch_credits(EpochCredit::new(11, 300, 100));
+
+        let versions = VoteStateVersions::from(&vote_state);
+        assert!(!versions.is_uninitialized());
+        let legacy = versions.convert_to_current();
+        assert_eq!(node_pubkey, legacy.node_pubkey);
+        assert_eq!(authorized_withdrawer, legacy.authorized_withdrawer);
+        assert_eq!(42, legacy.commission);
+        assert_eq!(&vec![(11, 300, 100)], legacy.epoch_credits());
+        assert_eq!(Some(authorized_voter), legacy.get_authorized_voter(11));
+        assert_eq!(Some(next_voter), legacy.get_authorized_voter(12));
+    }
+
+    #[test]
+    fn test_has_node_identity() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert!(vote_state.has_node_identity());
+
+        let commission_only = VoteState::new_for_tests(
+            Pubkey::default(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert!(!commission_only.has_node_identity());
+    }
+
+    #[test]
+    fn test_needs_migration() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        assert!(!VoteState::needs_migration(&data));
+
+        data[0] = VoteState::VOTE_STATE_VERSION - 1;
+        assert!(VoteState::needs_migration(&data));
+        data[0] = 1;
+        assert!(VoteState::needs_migration(&data));
+
+        assert!(!VoteState::needs_migration(bytemuck::bytes_of(
+            &VoteState::default()
+        )));
+        assert!(!VoteState::needs_migration(&[]));
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        vote_state.rank_assigned = true.into();
+        vote_state.rank = PodU16::from(7);
+        // The rank was appended after version 7
+        let stored_version = 7;
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        data[0] = stored_version;
+
+        // Fields appended since the stored version are dropped
+        let mut account = TestAccount::with_data(data);
+        let vote_account = account.info();
+        VoteState::migrate(&vote_account, &Rent::default()).unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        assert!(!VoteState::needs_migration(&data));
+        let migrated = VoteState::deserialize(&data).unwrap();
+        assert_eq!(vote_state.commission(), migrated.commission());
+        assert_eq!(
+            vote_state.authorized_withdrawer(),
+            migrated.authorized_withdrawer()
+        );
+        assert_eq!(None, migrated.rank());
+        drop(data);
+
+        // Older accounts smaller than the current layout are cleared in place
+        let size = VoteState::size_for_version(stored_version).unwrap();
+        let mut account = TestAccount::with_data(bytemuck::bytes_of(&vote_state)[..size].to_vec());
+        let vote_account = account.info();
+        VoteState::clear_vote_account_state(&vote_account).unwrap();
+        assert_eq!(vec![0; size], *vote_account.try_borrow_data().unwrap());
+    }
+
+    #[test]
+    fn test_try_set_commission() {
+        let epoch_schedule = EpochSchedule::without_warmup();
+        let first_half = epoch_schedule.get_first_slot_in_epoch(3);
+        let second_half = first_half + epoch_schedule.slots_per_epoch / 2 + 1;
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            10,
+            BlsPubkey::default(),
+        );
+
+        assert_eq!(
+            Err(VoteError::InvalidCommission),
+            vote_state.try_set_commission(101, None, first_half, &epoch_schedule)
+        );
+        assert_eq!(
+            Err(VoteError::CommissionExceedsMaximum),
+            vote_state.try_set_commission(50, Some(40), first_half, &epoch_schedule)
+        );
+        assert_eq!(10, vote_state.commission());
+
+        // Decreases are too late in the second half of the epoch
+        assert_eq!(
+            Err(VoteError::CommissionUpdateTooLate),
+            vote_state.try_set_commission(5, None, second_half, &epoch_schedule)
+        );
+        assert_eq!(10, vote_state.commission());
+
+        // Increases are always allowed
+        assert_eq!(
+            Ok(()),
+            vote_state.try_set_commission(100, None, second_half, &epoch_schedule)
+        );
+        assert_eq!(100, vote_state.commission());
+
+        assert_eq!(
+            Ok(()),
+            vote_state.try_set_commission(5, Some(40), first_half, &epoch_schedule)
+        );
+        assert_eq!(5, vote_state.commission());
+    }
+
+    #[test]
+    fn test_last_commission_change() {
+        let epoch_schedule = EpochSchedule::without_warmup();
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            10,
+            BlsPubkey::default(),
+        );
+        assert_eq!(0, vote_state.last_commission_change());
+
+        vote_state
+            .try_set_commission(25, None, 0, &epoch_schedule)
+            .unwrap();
+        assert_eq!(15, vote_state.last_commission_change());
+
+        vote_state
+            .try_set_commission(5, None, 0, &epoch_schedule)
+            .unwrap();
+        assert_eq!(-20, vote_state.last_commission_change());
+
+        // A rejected update leaves the last change in place
+        vote_state
+            .try_set_commission(101, None, 0, &epoch_schedule)
+            .unwrap_err();
+        assert_eq!(-20, vote_state.last_commission_change());
+
+        vote_state
+            .try_set_commission(5, None, 0, &epoch_schedule)
+            .unwrap();
+        assert_eq!(0, vote_state.last_commission_change());
+
+        // Changes beyond the range of an `i8` saturate
+        vote_state.set_commission_unchecked(255);
+        vote_state.record_commission_change(0);
+        assert_eq!(i8::MIN, vote_state.last_commission_change());
+        vote_state.record_commission_change(255);
+        assert_eq!(i8::MAX, vote_state.last_commission_change());
+    }
+
+    #[test]
+    fn test_max_withdraw() {
+        let rent = Rent::default();
+        let size = VoteState::size();
+        let reserve = VoteState::get_rent_exempt_reserve(&rent);
+        assert_eq!(0, VoteState::max_withdraw(reserve, size, &rent));
+        assert_eq!(0, VoteState::max_withdraw(reserve - 1, size, &rent));
+        assert_eq!(0, VoteState::max_withdraw(0, size, &rent));
+        assert_eq!(42, VoteState::max_withdraw(reserve + 42, size, &rent));
+
+        // Over-allocated accounts keep the rent of their whole data
+        let over_allocated_reserve = rent.minimum_balance(size + 100);
+        assert_eq!(
+            over_allocated_reserve - reserve,
+            VoteState::max_withdraw(over_allocated_reserve, size, &rent)
+        );
+        assert_eq!(
+            0,
+            VoteState::max_withdraw(over_allocated_reserve, size + 100, &rent)
+        );
+    }
+
+    #[test]
+    fn test_epoch_credits_ordered() {
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert!(vote_state.epoch_credits_ordered().is_empty());
+
+        vote_state.set_epoch_credits(EpochCredit::new(3, 150, 100));
+        let ordered = vote_state.epoch_credits_ordered();
+        assert_eq!(vec![EpochCredit::new(3, 150, 100)], ordered);
+        assert_eq!(ordered, vote_state.epoch_credits_ordered());
+
+        vote_state.set_epoch_credits(EpochCredit::new(4, 170, 150));
+        assert_eq!(
+            vec![EpochCredit::new(4, 170, 150)],
+            vote_state.epoch_credits_ordered()
+        );
+    }
+
+    #[test]
+    fn test_epoch_credits_iter() {
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        assert_eq!(None, vote_state.epoch_credits_iter().next());
+
+        // Each epoch replaces the retained entry
+        let mut credits = 0;
+        for epoch in 1..=5 {
+            let prev_credits = credits;
+            credits += 10 * epoch;
+            vote_state.set_epoch_credits(EpochCredit::new(epoch, credits, prev_credits));
+            assert_eq!(
+                vec![&EpochCredit::new(epoch, credits, prev_credits)],
+                vote_state.epoch_credits_iter().collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(
+            vote_state.epoch_credits_ordered(),
+            vote_state.epoch_credits_iter().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_credits_earned_since() {
+        let mut prior = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let mut current = prior;
+        assert_eq!(0, current.credits_earned_since(&prior));
+
+        // Same epoch growth
+        prior.set_epoch_credits(EpochCredit::new(10, 150, 100));
+        current.set_epoch_credits(EpochCredit::new(10, 180, 100));
+        assert_eq!(30, current.credits_earned_since(&prior));
+        assert_eq!(0, current.credits_earned_since(&current));
+
+        // Across an epoch rollover: 20 more credits in epoch 10, then 16 in epoch 11
+        current.set_epoch_credits(EpochCredit::new(11, 100 + 170 + 16, 100 + 170));
+        assert_eq!(20 + 16, current.credits_earned_since(&prior));
+
+        // From a freshly initialized account
+        let initial = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        current.set_epoch_credits(EpochCredit::new(11, 42, 0));
+        assert_eq!(42, current.credits_earned_since(&initial));
+    }
+
+    #[test]
+    fn test_partial_epoch_credits() {
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        vote_state.set_epoch_credits(EpochCredit::new(5, 300, 100));
+
+        // Full epoch
+        assert_eq!(200, vote_state.partial_epoch_credits(1, 1));
+        assert_eq!(200, vote_state.partial_epoch_credits(432_000, 432_000));
+
+        // Half epoch
+        assert_eq!(100, vote_state.partial_epoch_credits(1, 2));
+        assert_eq!(100, vote_state.partial_epoch_credits(216_000, 432_000));
+
+        // Intermediates do not overflow
+        vote_state.set_epoch_credits(EpochCredit::new(5, u64::MAX, 0));
+        assert_eq!(
+            u64::MAX / 2,
+            vote_state.partial_epoch_credits(u64::MAX / 2, u64::MAX)
+        );
+
+        assert_eq!(0, vote_state.partial_epoch_credits(1, 0));
+    }
+
+    #[test]
+    fn test_deserialize_from_account() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        account.data.extend_from_slice(&[0xff; 64]);
+        let vote_account = account.info();
+        let data = vote_account.try_borrow_data().unwrap();
+        assert!(VoteState::deserialize(&data).is_err());
+        assert_eq!(Ok(&vote_state), VoteState::deserialize_from_account(&data));
+        drop(data);
+
+        let mut account = TestAccount::with_data(vec![0; VoteState::size() - 1]);
+        let vote_account = account.info();
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            VoteState::deserialize_from_account(&vote_account.try_borrow_data().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_from_account_info() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        let mut account = TestAccount::new(&vote_state);
+        let mut vote_account = account.info();
+        assert_eq!(
+            vote_state,
+            *VoteState::from_account_info(&vote_account).unwrap()
+        );
+
+        let wrong_owner = Pubkey::new_unique();
+        vote_account.owner = &wrong_owner;
+        assert_eq!(
+            Err(ProgramError::InvalidAccountOwner),
+            VoteState::from_account_info(&vote_account).map(|_| ())
+        );
+
+        let mut account = TestAccount::with_data(vec![0; VoteState::size() - 1]);
+        let vote_account = account.info();
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            VoteState::from_account_info(&vote_account).map(|_| ())
+        );
+
+        // An older version is rejected even if the account is large enough
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        data[0] = VoteState::VOTE_STATE_VERSION - 1;
+        let mut account = TestAccount::with_data(data);
+        let vote_account = account.info();
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            VoteState::from_account_info(&vote_account).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_deserialize_versioned() {
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            7,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        vote_state.set_paused(true);
+        vote_state.set_collector_authority(Some(Pubkey::new_unique()));
+
+        // The current version is read as is, trailing data is ignored
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        data.extend_from_slice(&[0xff; 16]);
+        assert_eq!(vote_state, VoteState::deserialize_versioned(&data).unwrap());
+
+        // Older, smaller layouts are upgraded, with the appended fields zeroed
+        for version in [1, 3] {
+            let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+            data[0] = version;
+            data.truncate(VoteState::size_for_version(version).unwrap());
+            assert!(data.len() < VoteState::size());
+            let upgraded = VoteState::deserialize_versioned(&data).unwrap();
+            assert!(upgraded.is_current_version());
+            assert_eq!(vote_state.node_pubkey(), upgraded.node_pubkey());
+            assert_eq!(
+                vote_state.authorized_withdrawer(),
+                upgraded.authorized_withdrawer()
+            );
+            assert_eq!(42, upgraded.commission());
+            assert!(!upgraded.paused());
+        }
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        data[0] = 1;
+        data.truncate(VoteState::size_for_version(1).unwrap());
+        assert_eq!(
+            None,
+            VoteState::deserialize_versioned(&data)
+                .unwrap()
+                .collector_authority()
+        );
+
+        // Every supported version has a known size, which only grows
+        let sizes: Vec<usize> = VoteState::SUPPORTED_VERSIONS
+            .iter()
+            .map(|version| VoteState::size_for_version(*version).unwrap())
+            .collect();
+        assert!(sizes.windows(2).all(|sizes| sizes[0] < sizes[1]));
+        assert_eq!(Some(&VoteState::size()), sizes.last());
+
+        // Unsupported versions are rejected
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        for version in [0, VoteState::SUPPORTED_VERSIONS.len() as u8 + 1, u8::MAX] {
+            data[0] = version;
+            assert_eq!(
+                Err(ProgramError::InvalidAccountData),
+                VoteState::deserialize_versioned(&data)
+            );
+        }
+
+        // As is data truncated below the size of its version
+        let mut data = bytemuck::bytes_of(&vote_state).to_vec();
+        data.pop();
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            VoteState::deserialize_versioned(&data)
+        );
+        data[0] = 1;
+        data.truncate(VoteState::size_for_version(1).unwrap() - 1);
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            VoteState::deserialize_versioned(&data)
+        );
+        assert_eq!(
+            Err(ProgramError::InvalidAccountData),
+            VoteState::deserialize_versioned(&[])
+        );
+    }
+
+    #[test]
+    fn test_vote_state_builder() {
+        let authorized_voter = Pubkey::new_unique();
+        let vote_state = crate::state::VoteStateBuilder::new()
+            .commission(7)
+            .authorized_voter(authorized_voter, 3)
+            .build();
+
+        assert!(vote_state.is_initialized());
+        assert!(vote_state.is_current_version());
+        assert_eq!(7, vote_state.commission());
+        assert_eq!(authorized_voter, *vote_state.authorized_voter().voter());
+        assert_eq!(3, vote_state.authorized_voter().epoch());
+        assert_eq!(Pubkey::default(), *vote_state.node_pubkey());
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        let copy = vote_state;
+        assert_eq!(vote_state.state_hash(), copy.state_hash());
+
+        let mut other = vote_state;
+        other.set_commission_unchecked(43);
+        assert_ne!(vote_state.state_hash(), other.state_hash());
+    }
+
+    #[test]
+    fn test_matches_last_vote() {
+        let mut vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            0,
+            BlsPubkey::default(),
+        );
+        let block_id = Hash::new_unique();
+        let bank_hash = Hash::new_unique();
+        let vote = Vote::new_notarization_vote(42, block_id, bank_hash);
+
+        // Nothing has landed yet
+        assert!(!vote_state.matches_last_vote(&vote));
+        assert!(!vote_state.matches_last_vote(&Vote::new_finalization_vote(0)));
+
+        // A retry of the landed vote matches
+        vote_state.set_last_vote(VoteInstruction::Notarize, 42, block_id);
+        assert!(vote_state.matches_last_vote(&vote));
+        assert!(vote_state.matches_last_vote(&Vote::new_notarization_vote(42, block_id, bank_hash)));
+
+        // Different votes do not
+        for other in [
+            Vote::new_notarization_vote(43, block_id, bank_hash),
+            Vote::new_notarization_vote(42, Hash::new_unique(), bank_hash),
+            Vote::new_notarization_fallback_vote(42, block_id, bank_hash),
+            Vote::new_finalization_vote(42),
+            Vote::new_skip_vote(42),
+        ] {
+            assert!(!vote_state.matches_last_vote(&other));
+        }
+
+        vote_state.set_last_vote(VoteInstruction::Skip, 42, Hash::default());
+        assert!(vote_state.matches_last_vote(&Vote::new_skip_vote(42)));
+        assert!(!vote_state.matches_last_vote(&Vote::new_skip_fallback_vote(42)));
+        assert!(!vote_state.matches_last_vote(&vote));
+
+        // Fallback votes are recorded and matched as themselves
+        vote_state.set_last_vote(VoteInstruction::SkipFallback, 42, Hash::default());
+        assert!(vote_state.matches_last_vote(&Vote::new_skip_fallback_vote(42)));
+        assert!(!vote_state.matches_last_vote(&Vote::new_skip_vote(42)));
+    }
+
+    #[test]
+    fn test_semantically_eq() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            7,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+
+        let mut other = vote_state;
+        other.last_finalized_slot = PodSlot::from(1234);
+        other.finalize_count = PodU64::from(3);
+        assert_ne!(vote_state, other);
+        assert!(vote_state.semantically_eq(&other));
+
+        other.set_commission_unchecked(43);
+        assert!(!vote_state.semantically_eq(&other));
+
+        let mut other = vote_state;
+        other.set_epoch_credits(EpochCredit::new(7, 16, 0));
+        assert!(!vote_state.semantically_eq(&other));
+
+        let mut other = vote_state;
+        other.set_collector_authority(Some(Pubkey::new_unique()));
+        assert!(!vote_state.semantically_eq(&other));
+
+        let mut other = vote_state;
+        other.set_paused(true);
+        assert!(!vote_state.semantically_eq(&other));
+    }
+
+    #[test]
+    fn test_set_vote_account_state() {
+        let vote_state = VoteState::new_for_tests(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            Pubkey::new_unique(),
+            42,
+            BlsPubkey::default(),
+        );
+        for (len, expected) in [
+            (
+                VoteState::size() - 1,
+                Err(ProgramError::AccountDataTooSmall),
+            ),
+            (VoteState::size(), Ok(())),
+            (VoteState::size() + 10, Ok(())),
+        ] {
+            let mut account = TestAccount::with_data(vec![0; len]);
+            assert_eq!(
+                expected,
+                VoteState::set_vote_account_state(&account.info(), &vote_state)
+            );
+            let data = account.data;
+            if expected.is_ok() {
+                assert_eq!(
+                    &vote_state,
+                    VoteState::deserialize(&data[..VoteState::size()]).unwrap()
+                );
+                assert!(data[VoteState::size()..].iter().all(|byte| *byte == 0));
+            } else {
+                assert!(data.iter().all(|byte| *byte == 0));
+            }
+        }
+    }
 }
@@ -0,0 +1,74 @@
+//! Helpers shared by the unit tests
+
+use {
+    crate::{
+        instruction::derive_commission_config,
+        state::{CommissionConfig, VoteState},
+    },
+    solana_loader_v3_interface::state::UpgradeableLoaderState,
+    solana_program::{account_info::AccountInfo, bpf_loader_upgradeable, pubkey::Pubkey},
+};
+
+/// Backing storage for an account, lent to the processors as an `AccountInfo`
+pub(crate) struct TestAccount {
+    pub(crate) key: Pubkey,
+    pub(crate) owner: Pubkey,
+    pub(crate) lamports: u64,
+    pub(crate) data: Vec<u8>,
+}
+
+impl TestAccount {
+    /// A vote account holding `vote_state`
+    pub(crate) fn new(vote_state: &VoteState) -> Self {
+        Self::with_data(bytemuck::bytes_of(vote_state).to_vec())
+    }
+
+    /// An account owned by the program holding `data`
+    pub(crate) fn with_data(data: Vec<u8>) -> Self {
+        Self {
+            key: Pubkey::new_unique(),
+            owner: crate::id(),
+            lamports: 0,
+            data,
+        }
+    }
+
+    /// The `CommissionConfig` account, set by governance to `max_commission`
+    pub(crate) fn commission_config(max_commission: u8) -> Self {
+        Self {
+            key: derive_commission_config().0,
+            ..Self::with_data(bytemuck::bytes_of(&CommissionConfig { max_commission }).to_vec())
+        }
+    }
+
+    /// The `ProgramData` account of the program, recording
+    /// `upgrade_authority_address` as the governance authority
+    pub(crate) fn program_data(upgrade_authority_address: Option<Pubkey>) -> Self {
+        let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address,
+        })
+        .unwrap();
+        data.resize(UpgradeableLoaderState::size_of_programdata(64), 0);
+        Self {
+            key: crate::governance::program_data_address(),
+            owner: bpf_loader_upgradeable::id(),
+            lamports: 0,
+            data,
+        }
+    }
+
+    /// A writable, non-signer view of the account
+    pub(crate) fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            false,
+            true,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            false,
+            0,
+        )
+    }
+}
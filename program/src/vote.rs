@@ -2,16 +2,25 @@
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use solana_hash::Hash;
-use solana_program::clock::Slot;
+use solana_hash::{Hash, HASH_BYTES};
+use solana_program::clock::{Slot, NUM_CONSECUTIVE_LEADER_SLOTS};
 use solana_program::instruction::Instruction;
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 
-use crate::instruction::{self, decode_instruction_data, decode_instruction_type, VoteInstruction};
+use crate::domains;
+use crate::error::VoteError;
+use crate::instruction::{
+    self, decode_instruction_data, decode_instruction_type, strip_discriminator_prefix,
+    VoteInstruction,
+};
 use crate::state::PodSlot;
 use crate::vote_processor::NotarizationVoteInstructionData;
 
+/// How far behind the clock slot a vote may be while still counting as live
+/// rather than catch-up: one leader window
+pub const LIVE_VOTE_THRESHOLD_SLOTS: Slot = NUM_CONSECUTIVE_LEADER_SLOTS;
+
 /// Enum that clients can use to parse and create the vote
 /// structures expected by the program
 #[cfg_attr(
@@ -68,7 +77,8 @@ impl Vote {
 
     /// If this instruction represented by `instruction_data` is a vote
     pub fn is_simple_vote(instruction_data: &[u8]) -> Result<bool, ProgramError> {
-        let instruction_type = decode_instruction_type(instruction_data)?;
+        let instruction_type =
+            decode_instruction_type(strip_discriminator_prefix(instruction_data)?)?;
         Ok(matches!(
             instruction_type,
             VoteInstruction::Notarize
@@ -80,43 +90,64 @@ impl Vote {
     }
 
     /// Deserializes instruction represented by `instruction_data` into a `Vote`
-    /// Must be guarded by `is_simple_vote`
+    /// Errors if the instruction is not a vote, callers can guard with `is_simple_vote`
     pub fn deserialize_simple_vote(instruction_data: &[u8]) -> Result<Vote, ProgramError> {
-        debug_assert!(Self::is_simple_vote(instruction_data)?);
+        Self::try_from_instruction_data(instruction_data)?
+            .ok_or_else(|| VoteError::InvalidInstruction.into())
+    }
+
+    /// Deserializes instruction represented by `instruction_data` into a `Vote`
+    /// Returns `Ok(None)` if the instruction is not a vote, and an error if the
+    /// vote data is malformed
+    pub fn try_from_instruction_data(
+        instruction_data: &[u8],
+    ) -> Result<Option<Vote>, ProgramError> {
+        let instruction_data = strip_discriminator_prefix(instruction_data)?;
         let instruction_type = decode_instruction_type(instruction_data)?;
-        match instruction_type {
+        let vote = match instruction_type {
             VoteInstruction::Notarize => {
                 let notarization_vote =
                     decode_instruction_data::<NotarizationVoteInstructionData>(instruction_data)?;
-                Ok(Vote::from(NotarizationVote::new_internal(
-                    notarization_vote,
-                )))
+                Vote::from(NotarizationVote::new_internal(notarization_vote))
             }
             VoteInstruction::Finalize => {
                 let finalization_slot = decode_instruction_data::<PodSlot>(instruction_data)?;
-                Ok(Vote::from(FinalizationVote::new_internal(
-                    finalization_slot,
-                )))
+                Vote::from(FinalizationVote::new_internal(finalization_slot))
             }
             VoteInstruction::Skip => {
                 let skip_slot = decode_instruction_data::<PodSlot>(instruction_data)?;
-                Ok(Vote::from(SkipVote::new_internal(skip_slot)))
+                Vote::from(SkipVote::new_internal(skip_slot))
             }
             VoteInstruction::NotarizeFallback => {
                 let notarization_fallback_vote =
                     decode_instruction_data::<NotarizationVoteInstructionData>(instruction_data)?;
-                Ok(Vote::from(NotarizationFallbackVote::new_internal(
+                Vote::from(NotarizationFallbackVote::new_internal(
                     notarization_fallback_vote,
-                )))
+                ))
             }
             VoteInstruction::SkipFallback => {
                 let skip_fallback_slot = decode_instruction_data::<PodSlot>(instruction_data)?;
-                Ok(Vote::from(SkipFallbackVote::new_internal(
-                    skip_fallback_slot,
-                )))
+                Vote::from(SkipFallbackVote::new_internal(skip_fallback_slot))
             }
-            _ => panic!("Programmer error"),
-        }
+            VoteInstruction::InitializeAccount
+            | VoteInstruction::Authorize
+            | VoteInstruction::AuthorizeChecked
+            | VoteInstruction::AuthorizeWithSeed
+            | VoteInstruction::AuthorizeCheckedWithSeed
+            | VoteInstruction::Withdraw
+            | VoteInstruction::UpdateValidatorIdentity
+            | VoteInstruction::UpdateCommission
+            | VoteInstruction::SetCollectorAuthority
+            | VoteInstruction::SetPaused
+            | VoteInstruction::Realloc
+            | VoteInstruction::AssignRank
+            | VoteInstruction::SetCommissionConfig
+            | VoteInstruction::Deinitialize
+            | VoteInstruction::GetCredits
+            | VoteInstruction::UpdateConfig
+            | VoteInstruction::FinalizeMany => return Ok(None),
+        };
+        Ok(Some(vote))
     }
 
     /// Generate a vote instruction from this vote
@@ -145,6 +176,12 @@ impl Vote {
         }
     }
 
+    /// Whether this is a live vote, for a slot within `LIVE_VOTE_THRESHOLD_SLOTS`
+    /// of `clock_slot` or later, as opposed to a catch-up vote for a past slot
+    pub fn is_live(&self, clock_slot: Slot) -> bool {
+        self.slot() >= clock_slot.saturating_sub(LIVE_VOTE_THRESHOLD_SLOTS)
+    }
+
     /// The block id associated with the block which was voted for
     pub fn block_id(&self) -> Option<&Hash> {
         match self {
@@ -192,6 +229,96 @@ impl Vote {
     pub fn is_notarization_or_finalization(&self) -> bool {
         matches!(self, Self::Notarize(_) | Self::Finalize(_))
     }
+
+    /// The domain separation tag for this vote type
+    pub fn domain(&self) -> &'static [u8] {
+        match self {
+            Self::Notarize(_) => domains::NOTARIZE_DOMAIN,
+            Self::Finalize(_) => domains::FINALIZE_DOMAIN,
+            Self::Skip(_) => domains::SKIP_DOMAIN,
+            Self::NotarizeFallback(_) => domains::NOTARIZE_FALLBACK_DOMAIN,
+            Self::SkipFallback(_) => domains::SKIP_FALLBACK_DOMAIN,
+        }
+    }
+
+    /// The canonical bytes signed for this vote: the domain tag, followed by
+    /// the little endian slot and, for notarization style votes, the block id
+    /// and replayed bank hash
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.domain().to_vec();
+        bytes.extend_from_slice(&self.slot().to_le_bytes());
+        if let (Some(block_id), Some(replayed_bank_hash)) =
+            (self.block_id(), self.replayed_bank_hash())
+        {
+            bytes.extend_from_slice(block_id.as_ref());
+            bytes.extend_from_slice(replayed_bank_hash.as_ref());
+        }
+        bytes
+    }
+
+    /// Parse the output of `signing_bytes` back into the vote. Fails with
+    /// `ProgramError::InvalidArgument` for an unknown domain tag or a payload of
+    /// the wrong length for the vote type. The unused replayed slot is not
+    /// signed and is always zero in the result
+    pub fn from_signing_bytes(bytes: &[u8]) -> Result<Vote, ProgramError> {
+        let read_slot = |payload: &[u8]| {
+            <[u8; 8]>::try_from(payload)
+                .map(Slot::from_le_bytes)
+                .map_err(|_| ProgramError::InvalidArgument)
+        };
+        let read_notarization = |payload: &[u8]| {
+            if payload.len() != 8 + 2 * HASH_BYTES {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let (slot, hashes) = payload.split_at(8);
+            let (block_id, replayed_bank_hash) = hashes.split_at(HASH_BYTES);
+            Ok((
+                read_slot(slot)?,
+                Hash::new_from_array(block_id.try_into().unwrap()),
+                Hash::new_from_array(replayed_bank_hash.try_into().unwrap()),
+            ))
+        };
+
+        // No tag is a prefix of another, so at most one matches
+        if let Some(payload) = bytes.strip_prefix(domains::NOTARIZE_DOMAIN) {
+            let (slot, block_id, replayed_bank_hash) = read_notarization(payload)?;
+            Ok(Self::new_notarization_vote(
+                slot,
+                block_id,
+                replayed_bank_hash,
+            ))
+        } else if let Some(payload) = bytes.strip_prefix(domains::FINALIZE_DOMAIN) {
+            Ok(Self::new_finalization_vote(read_slot(payload)?))
+        } else if let Some(payload) = bytes.strip_prefix(domains::SKIP_DOMAIN) {
+            Ok(Self::new_skip_vote(read_slot(payload)?))
+        } else if let Some(payload) = bytes.strip_prefix(domains::NOTARIZE_FALLBACK_DOMAIN) {
+            let (slot, block_id, replayed_bank_hash) = read_notarization(payload)?;
+            Ok(Self::new_notarization_fallback_vote(
+                slot,
+                block_id,
+                replayed_bank_hash,
+            ))
+        } else if let Some(payload) = bytes.strip_prefix(domains::SKIP_FALLBACK_DOMAIN) {
+            Ok(Self::new_skip_fallback_vote(read_slot(payload)?))
+        } else {
+            Err(ProgramError::InvalidArgument)
+        }
+    }
+
+    /// Strict sanity check of the vote contents, for clients to opt into.
+    /// Notarization style votes whose `block_id` and `replayed_bank_hash` are
+    /// the same non-zero hash are rejected, as these refer to different blocks
+    /// after APE and should generally differ
+    pub fn validate(&self) -> Result<(), VoteError> {
+        match (self.block_id(), self.replayed_bank_hash()) {
+            (Some(block_id), Some(replayed_bank_hash))
+                if block_id == replayed_bank_hash && *block_id != Hash::default() =>
+            {
+                Err(VoteError::BlockIdMatchesReplayedBankHash)
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl From<NotarizationVote> for Vote {
@@ -224,6 +351,28 @@ impl From<SkipFallbackVote> for Vote {
     }
 }
 
+impl From<NotarizationVote> for NotarizationFallbackVote {
+    fn from(vote: NotarizationVote) -> Self {
+        Self {
+            slot: vote.slot,
+            block_id: vote.block_id,
+            _replayed_slot: vote._replayed_slot,
+            replayed_bank_hash: vote.replayed_bank_hash,
+        }
+    }
+}
+
+impl From<NotarizationFallbackVote> for NotarizationVote {
+    fn from(vote: NotarizationFallbackVote) -> Self {
+        Self {
+            slot: vote.slot,
+            block_id: vote.block_id,
+            _replayed_slot: vote._replayed_slot,
+            replayed_bank_hash: vote.replayed_bank_hash,
+        }
+    }
+}
+
 /// A notarization vote
 #[cfg_attr(
     feature = "frozen-abi",
@@ -417,3 +566,215 @@ impl SkipFallbackVote {
         self.slot
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use solana_hash::Hash;
+    use solana_program::program_error::ProgramError;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::domains;
+    use crate::error::VoteError;
+    use crate::instruction::{self, AuthorityType};
+    use crate::vote::{
+        NotarizationFallbackVote, NotarizationVote, Vote, LIVE_VOTE_THRESHOLD_SLOTS,
+    };
+
+    #[test]
+    fn test_is_live() {
+        let clock_slot = 1000;
+        assert!(Vote::new_skip_vote(clock_slot).is_live(clock_slot));
+        assert!(Vote::new_finalization_vote(clock_slot + 1).is_live(clock_slot));
+        assert!(Vote::new_skip_vote(clock_slot - LIVE_VOTE_THRESHOLD_SLOTS).is_live(clock_slot));
+        assert!(
+            !Vote::new_skip_vote(clock_slot - LIVE_VOTE_THRESHOLD_SLOTS - 1).is_live(clock_slot)
+        );
+        assert!(
+            !Vote::new_notarization_vote(3, Hash::new_unique(), Hash::new_unique())
+                .is_live(clock_slot)
+        );
+
+        // Early slots do not underflow
+        assert!(Vote::new_skip_vote(0).is_live(1));
+    }
+
+    #[test]
+    fn test_validate_block_id_equals_replayed_bank_hash() {
+        let hash = Hash::new_unique();
+        let vote = Vote::new_notarization_vote(42, hash, hash);
+        assert_eq!(
+            Err(VoteError::BlockIdMatchesReplayedBankHash),
+            vote.validate()
+        );
+
+        let vote = Vote::new_notarization_fallback_vote(42, hash, hash);
+        assert_eq!(
+            Err(VoteError::BlockIdMatchesReplayedBankHash),
+            vote.validate()
+        );
+
+        // Zero hashes are not flagged
+        let vote = Vote::new_notarization_vote(42, Hash::default(), Hash::default());
+        assert!(vote.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_block_id_distinct_from_replayed_bank_hash() {
+        let vote = Vote::new_notarization_vote(42, Hash::new_unique(), Hash::new_unique());
+        assert!(vote.validate().is_ok());
+
+        let vote = Vote::new_notarization_fallback_vote(42, Hash::new_unique(), Hash::new_unique());
+        assert!(vote.validate().is_ok());
+
+        assert!(Vote::new_finalization_vote(42).validate().is_ok());
+        assert!(Vote::new_skip_vote(42).validate().is_ok());
+        assert!(Vote::new_skip_fallback_vote(42).validate().is_ok());
+    }
+
+    #[test]
+    fn test_try_from_instruction_data_non_vote() {
+        let ix = instruction::authorize(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            AuthorityType::Voter,
+        );
+        assert_eq!(Ok(None), Vote::try_from_instruction_data(&ix.data));
+        assert_eq!(
+            Err(ProgramError::from(VoteError::InvalidInstruction)),
+            Vote::deserialize_simple_vote(&ix.data)
+        );
+    }
+
+    #[test]
+    fn test_try_from_instruction_data_valid_vote() {
+        for vote in [
+            Vote::new_notarization_vote(42, Hash::new_unique(), Hash::new_unique()),
+            Vote::new_finalization_vote(42),
+            Vote::new_skip_vote(42),
+            Vote::new_notarization_fallback_vote(42, Hash::new_unique(), Hash::new_unique()),
+            Vote::new_skip_fallback_vote(42),
+        ] {
+            let ix = vote.to_vote_instruction(Pubkey::new_unique(), Pubkey::new_unique());
+            assert_eq!(Ok(Some(vote)), Vote::try_from_instruction_data(&ix.data));
+        }
+    }
+
+    #[test]
+    fn test_try_from_instruction_data_truncated_vote() {
+        let vote = Vote::new_notarization_vote(42, Hash::new_unique(), Hash::new_unique());
+        let ix = vote.to_vote_instruction(Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            Vote::try_from_instruction_data(&ix.data[..ix.data.len() - 1])
+        );
+
+        let ix =
+            Vote::new_skip_vote(42).to_vote_instruction(Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            Vote::try_from_instruction_data(&ix.data[..4])
+        );
+        assert_eq!(
+            Err(ProgramError::InvalidInstructionData),
+            Vote::try_from_instruction_data(&[])
+        );
+    }
+
+    #[test]
+    fn test_notarization_fallback_conversion_round_trip() {
+        let block_id = Hash::new_unique();
+        let bank_hash = Hash::new_unique();
+        let vote = NotarizationVote::new(42, block_id, 0, bank_hash);
+
+        let fallback = NotarizationFallbackVote::from(vote);
+        assert_eq!(42, fallback.slot());
+        assert_eq!(&block_id, fallback.block_id());
+        assert_eq!(&bank_hash, fallback.replayed_bank_hash());
+
+        let round_trip = NotarizationVote::from(fallback);
+        assert_eq!(vote, round_trip);
+        assert_eq!(42, round_trip.slot());
+        assert_eq!(&block_id, round_trip.block_id());
+        assert_eq!(&bank_hash, round_trip.replayed_bank_hash());
+    }
+
+    #[test]
+    fn test_signing_bytes_domain() {
+        let block_id = Hash::new_unique();
+        let bank_hash = Hash::new_unique();
+        let votes = [
+            (
+                Vote::new_notarization_vote(42, block_id, bank_hash),
+                domains::NOTARIZE_DOMAIN,
+            ),
+            (Vote::new_finalization_vote(42), domains::FINALIZE_DOMAIN),
+            (Vote::new_skip_vote(42), domains::SKIP_DOMAIN),
+            (
+                Vote::new_notarization_fallback_vote(42, block_id, bank_hash),
+                domains::NOTARIZE_FALLBACK_DOMAIN,
+            ),
+            (
+                Vote::new_skip_fallback_vote(42),
+                domains::SKIP_FALLBACK_DOMAIN,
+            ),
+        ];
+
+        for (vote, domain) in votes {
+            let bytes = vote.signing_bytes();
+            assert!(bytes.starts_with(domain));
+            assert_eq!(42u64.to_le_bytes(), bytes[domain.len()..domain.len() + 8]);
+            if vote.block_id().is_some() {
+                assert_eq!(domain.len() + 8 + 64, bytes.len());
+            } else {
+                assert_eq!(domain.len() + 8, bytes.len());
+            }
+        }
+
+        // No tag is a prefix of another, so the vote type is unambiguous
+        for (i, (_, domain)) in votes.iter().enumerate() {
+            for (j, (_, other)) in votes.iter().enumerate() {
+                assert!(i == j || !other.starts_with(domain));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_signing_bytes() {
+        let block_id = Hash::new_unique();
+        let bank_hash = Hash::new_unique();
+        let votes = [
+            Vote::new_notarization_vote(42, block_id, bank_hash),
+            Vote::new_finalization_vote(42),
+            Vote::new_skip_vote(42),
+            Vote::new_notarization_fallback_vote(42, block_id, bank_hash),
+            Vote::new_skip_fallback_vote(42),
+        ];
+
+        for vote in votes {
+            let bytes = vote.signing_bytes();
+            assert_eq!(Ok(vote), Vote::from_signing_bytes(&bytes));
+
+            // Truncated and padded payloads are not canonical
+            assert_eq!(
+                Err(ProgramError::InvalidArgument),
+                Vote::from_signing_bytes(&bytes[..bytes.len() - 1])
+            );
+            let mut padded = bytes.clone();
+            padded.push(0);
+            assert_eq!(
+                Err(ProgramError::InvalidArgument),
+                Vote::from_signing_bytes(&padded)
+            );
+        }
+
+        assert_eq!(
+            Err(ProgramError::InvalidArgument),
+            Vote::from_signing_bytes(b"ALPENGLOW_UNKNOWN_V1")
+        );
+        assert_eq!(
+            Err(ProgramError::InvalidArgument),
+            Vote::from_signing_bytes(&[])
+        );
+    }
+}
@@ -1,13 +1,23 @@
+//! Vote processing and credit computation
+
+use std::ops::RangeInclusive;
+use std::slice;
+
 use bytemuck::{Pod, Zeroable};
 use solana_program::account_info::AccountInfo;
 use solana_program::clock::Clock;
+use solana_program::clock::Epoch;
 use solana_program::clock::Slot;
 use solana_program::hash::Hash;
+use solana_program::msg;
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
+use solana_program::slot_hashes::MAX_ENTRIES;
 use solana_program::sysvar::slot_hashes::PodSlotHashes;
+use spl_pod::primitives::PodU64;
 
 use crate::error::VoteError;
+use crate::instruction::{check_vote_many_slots, VoteInstruction};
 use crate::state::{PodSlot, VoteState};
 
 pub(crate) const CURRENT_NOTARIZE_VOTE_VERSION: u8 = 1;
@@ -20,6 +30,28 @@ pub const VOTE_CREDITS_GRACE_SLOTS: u64 = 2;
 /// slots that land within the grace period. After that grace period, vote credits are reduced.
 pub const VOTE_CREDITS_MAXIMUM_PER_SLOT: u64 = 16;
 
+/// The most credits the votes of a single instruction may award, half of what a
+/// full batch of `MAX_VOTE_MANY_SLOTS` votes at the maximum would earn. Batches
+/// earning more fail with `VoteError::CreditsPerTxExceeded`
+pub const MAX_CREDITS_PER_TRANSACTION: u64 = 256;
+
+/// With the `fresh-slot-hashes` feature, notarization votes fail with
+/// `VoteError::StaleSlotHashes` when the newest slot hashes entry is further than
+/// this behind the clock slot
+pub const MAX_SLOT_HASHES_AGE: Slot = 64;
+
+/// The most slots a skip range may cover, the number of entries the slot hashes
+/// sysvar retains: slots further back can not be checked for having been skipped
+pub const MAX_SKIP_RANGE: u64 = MAX_ENTRIES as u64;
+
+/// Prefix of the structured log lines emitted with the `events` feature, in the
+/// form `event:<name> <key>=<value> ...`, so indexers can filter program logs
+pub const EVENT_PREFIX: &str = "event:";
+
+/// Credits tracked by a vote account can not grow past this; an award that would exceed it
+/// fails with `VoteError::CreditsOverflow` rather than silently saturating.
+pub const VOTE_CREDITS_CAP: u64 = u64::MAX;
+
 /// A notarization vote, the data expected by
 /// `VoteInstruction::Notarize` and `VoteInstruction::NotarizeFallback`
 #[repr(C, packed)]
@@ -48,6 +80,23 @@ pub(crate) struct NotarizationVoteInstructionData {
 unsafe impl Zeroable for NotarizationVoteInstructionData {}
 unsafe impl Pod for NotarizationVoteInstructionData {}
 
+/// Length of the data following the discriminant in `VoteInstruction::Notarize`
+/// and `VoteInstruction::NotarizeFallback`: the version, slot, block id,
+/// replayed slot and replayed bank hash
+pub const NOTARIZATION_VOTE_DATA_LEN: usize = 81;
+
+const _: () =
+    assert!(std::mem::size_of::<NotarizationVoteInstructionData>() == NOTARIZATION_VOTE_DATA_LEN);
+
+/// The latency of a vote for `voted_for_slot` landing in `current_slot`.
+/// Saturates at zero for votes on future slots, and is capped at `u8::MAX`
+/// to match the latency recorded by the legacy vote program
+pub fn compute_vote_latency(voted_for_slot: Slot, current_slot: Slot) -> u64 {
+    current_slot
+        .saturating_sub(voted_for_slot)
+        .min(u64::from(u8::MAX))
+}
+
 /// Credits are awarded as a piece-wise linear function; up to a certain amount of block latency,
 /// the vote program awards the maximum number of credits. Then, the number of awarded credits goes
 /// down at a rate of 1 credit per block. The minimum number of awarded credits is 1.
@@ -68,6 +117,29 @@ fn latency_to_credits(latency: u64) -> u64 {
     }
 }
 
+/// The `(latency, credits)` pairs of the credit curve, from zero latency up to
+/// the first latency that only earns the minimum of 1 credit
+pub fn credits_for_latency_table() -> Vec<(u64, u64)> {
+    let floor = VOTE_CREDITS_MAXIMUM_PER_SLOT + VOTE_CREDITS_GRACE_SLOTS;
+    (0..floor)
+        .map(|latency| (latency, latency_to_credits(latency)))
+        .collect()
+}
+
+/// The credits lost by a vote landing with `latency` compared to one landing
+/// within the grace period
+pub fn credit_loss(latency: u64) -> u64 {
+    VOTE_CREDITS_MAXIMUM_PER_SLOT.saturating_sub(latency_to_credits(latency))
+}
+
+/// Add `earned_credits` to `credits`, erroring if the result exceeds `VOTE_CREDITS_CAP`
+fn add_credits(credits: u64, earned_credits: u64) -> Result<u64, VoteError> {
+    if earned_credits > VOTE_CREDITS_CAP.saturating_sub(credits) {
+        return Err(VoteError::CreditsOverflow);
+    }
+    Ok(credits.saturating_add(earned_credits))
+}
+
 fn set_credits(
     vote_state: &mut VoteState,
     epoch: u64,
@@ -76,33 +148,84 @@ fn set_credits(
     let epoch_credits = &mut vote_state.epoch_credits;
 
     if epoch == epoch_credits.epoch() {
-        epoch_credits.set_credits(epoch_credits.credits().saturating_add(earned_credits));
+        epoch_credits.set_credits(add_credits(epoch_credits.credits(), earned_credits)?);
         Ok(())
     } else {
-        let prev_credits = epoch_credits
-            .prev_credits()
-            .saturating_add(epoch_credits.credits());
+        let prev_credits = add_credits(epoch_credits.prev_credits(), epoch_credits.credits())?;
+        let credits = add_credits(prev_credits, earned_credits)?;
 
         epoch_credits.set_epoch(epoch);
         epoch_credits.set_prev_credits(prev_credits);
-        epoch_credits.set_credits(earned_credits.saturating_add(prev_credits));
+        epoch_credits.set_credits(credits);
         Ok(())
     }
 }
 
-/// Award credits based on latency of `vote_slot`
+/// Bump a vote counter
+fn increment_vote_count(vote_count: &mut PodU64) {
+    *vote_count = PodU64::from(u64::from(*vote_count).saturating_add(1));
+}
+
+/// Award credits based on latency of `vote_slot`, drawn from the
+/// `remaining_credits` the current instruction may still award
 fn award_credits(
     vote_state: &mut VoteState,
     vote_slot: u64,
     clock: &Clock,
+    remaining_credits: &mut u64,
 ) -> Result<(), ProgramError> {
     // NOTE: clock.slot >= vote_slot; otherwise, replay_bank_hash_checks would have returned an
     // error (vote.slot would not be in our slot hashes). Eventually, just use unchecked_sub.
-    let earned_credits = latency_to_credits(clock.slot.saturating_sub(vote_slot));
+    let earned_credits = latency_to_credits(compute_vote_latency(vote_slot, clock.slot));
+    *remaining_credits = remaining_credits
+        .checked_sub(earned_credits)
+        .ok_or(VoteError::CreditsPerTxExceeded)?;
     // Although this vote might be for a previous epoch, the checks in the caller
     // ensure that this is a new vote. We mirror the logic in the previous vote
     // program and award credits based on `clock.epoch`
-    set_credits(vote_state, clock.epoch, earned_credits)
+    set_credits(vote_state, clock.epoch, earned_credits)?;
+    if cfg!(feature = "events") {
+        msg!(
+            "{}",
+            credits_awarded_event(vote_slot, clock.epoch, earned_credits)
+        );
+    }
+    Ok(())
+}
+
+/// The `credits_awarded` event line logged by `award_credits`
+fn credits_awarded_event(vote_slot: Slot, epoch: Epoch, credits: u64) -> String {
+    format!("{EVENT_PREFIX}credits_awarded slot={vote_slot} epoch={epoch} credits={credits}")
+}
+
+/// Whether `slot` has an entry in `slot_hashes`, and so can be notarized on
+/// this fork. Unreadable slot hashes have no votable slots
+pub fn is_slot_votable(slot: Slot, slot_hashes: &PodSlotHashes) -> bool {
+    slot_hash(slot, slot_hashes).is_some()
+}
+
+/// The bank hash of `slot` in `slot_hashes`, if it has an entry there
+fn slot_hash(slot: Slot, slot_hashes: &PodSlotHashes) -> Option<Hash> {
+    slot_hashes.get(&slot).ok().flatten()
+}
+
+/// Why `vote_slot` has no entry in `slot_hashes`: it has not been replayed
+/// yet, it has been pruned, or it was skipped on this fork
+fn missing_slot_hash_error(vote_slot: Slot, slot_hashes: &PodSlotHashes) -> ProgramError {
+    // Slot hashes are ordered newest to oldest
+    let Ok(entries) = slot_hashes.as_slice() else {
+        return VoteError::MissingSlotHashesSysvar.into();
+    };
+    let (Some(newest), Some(oldest)) = (entries.first(), entries.last()) else {
+        return VoteError::SlotHashesMissingKey.into();
+    };
+    if vote_slot > newest.slot {
+        VoteError::NotarizeSlotNotReplayed.into()
+    } else if vote_slot < oldest.slot {
+        VoteError::NotarizeSlotTooOld.into()
+    } else {
+        VoteError::SlotHashesMissingKey.into()
+    }
 }
 
 /// Award credits for notarization vote
@@ -112,18 +235,21 @@ fn award_notarization_credits(
     bank_hash: Hash,
     clock: &Clock,
     slot_hashes: &PodSlotHashes,
+    remaining_credits: &mut u64,
 ) -> Result<(), ProgramError> {
+    // A zero bank hash is never a valid replay result, reject it before it can be
+    // compared against a degenerate slot hashes entry
+    if bank_hash == Hash::default() {
+        return Err(VoteError::ReplayBankHashMismatch.into());
+    }
+    check_slot_hashes_fresh(slot_hashes, clock.slot, cfg!(feature = "fresh-slot-hashes"))?;
+
     // We must have already executed `notarized_slot` and stored the associated bank hash
     // (error out otherwise). Ensure that our bank hash matches what we observe.
-    if bank_hash
-        != slot_hashes
-            .get(&vote_slot)
-            .map_err(|_| ProgramError::from(VoteError::MissingSlotHashesSysvar))?
-            .ok_or::<ProgramError>(VoteError::SlotHashesMissingKey.into())?
-    {
-        Err(VoteError::ReplayBankHashMismatch.into())
-    } else {
-        award_credits(vote_state, vote_slot, clock)
+    match slot_hash(vote_slot, slot_hashes) {
+        None => Err(missing_slot_hash_error(vote_slot, slot_hashes)),
+        Some(slot_hash) if slot_hash != bank_hash => Err(VoteError::ReplayBankHashMismatch.into()),
+        Some(_) => award_credits(vote_state, vote_slot, clock, remaining_credits),
     }
 }
 
@@ -132,8 +258,9 @@ fn award_finalization_credits(
     vote_state: &mut VoteState,
     vote_slot: Slot,
     clock: &Clock,
+    remaining_credits: &mut u64,
 ) -> Result<(), ProgramError> {
-    award_credits(vote_state, vote_slot, clock)
+    award_credits(vote_state, vote_slot, clock, remaining_credits)
 }
 
 /// Award credits for skip votes
@@ -142,6 +269,7 @@ fn award_skip_credits(
     skip_slot: Slot,
     clock: &Clock,
     slot_hashes: &PodSlotHashes,
+    remaining_credits: &mut u64,
 ) -> Result<(), ProgramError> {
     if skip_slot >= clock.slot {
         return Err(VoteError::SkipSlotExceedsCurrentSlot.into());
@@ -157,8 +285,80 @@ fn award_skip_credits(
     if hash.is_some() {
         Err(VoteError::SkipSlotPresent.into())
     } else {
-        award_credits(vote_state, skip_slot, clock)
+        award_credits(vote_state, skip_slot, clock, remaining_credits)
+    }
+}
+
+/// Reject votes while the withdraw authority has paused voting
+fn check_not_paused(vote_state: &VoteState) -> Result<(), ProgramError> {
+    if vote_state.paused() {
+        return Err(VoteError::VotingPaused.into());
+    }
+    Ok(())
+}
+
+/// Reject votes from an account without a node identity
+fn check_has_node_identity(vote_state: &VoteState) -> Result<(), ProgramError> {
+    if !vote_state.has_node_identity() {
+        return Err(VoteError::MissingNodeIdentity.into());
+    }
+    Ok(())
+}
+
+/// In `strict` mode, reject notarizations at or below the last finalized slot
+fn check_notarize_not_finalized(
+    vote_state: &VoteState,
+    vote_slot: Slot,
+    strict: bool,
+) -> Result<(), ProgramError> {
+    if strict && vote_slot <= vote_state.last_finalized_slot() {
+        return Err(VoteError::NotarizeFinalizedSlot.into());
+    }
+    Ok(())
+}
+
+/// In `strict` mode, reject a `slot_hashes` whose newest entry is more than
+/// `MAX_SLOT_HASHES_AGE` slots behind `clock_slot`
+fn check_slot_hashes_fresh(
+    slot_hashes: &PodSlotHashes,
+    clock_slot: Slot,
+    strict: bool,
+) -> Result<(), ProgramError> {
+    if !strict {
+        return Ok(());
+    }
+    // Slot hashes are ordered newest to oldest
+    let entries = slot_hashes
+        .as_slice()
+        .map_err(|_| VoteError::MissingSlotHashesSysvar)?;
+    match entries.first() {
+        Some(newest) if clock_slot.saturating_sub(newest.slot) <= MAX_SLOT_HASHES_AGE => Ok(()),
+        _ => Err(VoteError::StaleSlotHashes.into()),
+    }
+}
+
+/// Reject skip ranges covering more than `MAX_SKIP_RANGE` slots
+pub fn check_skip_range(range: &RangeInclusive<Slot>) -> Result<(), VoteError> {
+    // `end - start + 1 > MAX_SKIP_RANGE` without overflowing on a full range
+    if range.end().saturating_sub(*range.start()) >= MAX_SKIP_RANGE {
+        return Err(VoteError::SkipRangeTooLarge);
     }
+    Ok(())
+}
+
+/// The slots in `range` that a skip vote would earn credits for: slots before
+/// `clock_slot` that are absent from `slot_hashes`. Fails for ranges longer
+/// than `MAX_SKIP_RANGE`
+pub fn skippable_slots(
+    range: RangeInclusive<Slot>,
+    slot_hashes: &PodSlotHashes,
+    clock_slot: Slot,
+) -> Result<Vec<Slot>, VoteError> {
+    check_skip_range(&range)?;
+    Ok(range
+        .take_while(|slot| *slot < clock_slot)
+        .filter(|slot| matches!(slot_hashes.get(slot), Ok(None)))
+        .collect())
 }
 
 pub(crate) fn process_notarization_vote(
@@ -167,9 +367,10 @@ pub(crate) fn process_notarization_vote(
     clock: &Clock,
     slot_hashes: &PodSlotHashes,
     vote: &NotarizationVoteInstructionData,
+    kind: VoteInstruction,
 ) -> Result<(), ProgramError> {
-    let mut vote_state = vote_account.data.borrow_mut();
-    let vote_state = bytemuck::from_bytes_mut::<VoteState>(&mut vote_state);
+    let mut data = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut data)?;
 
     let vote_slot = vote.slot.into();
 
@@ -177,17 +378,28 @@ pub(crate) fn process_notarization_vote(
         return Err(VoteError::VersionMismatch.into());
     }
 
-    if vote_state.authorized_voter.voter != *vote_authority {
+    vote_state.promote_next_authorized_voter(clock.epoch);
+    if vote_state.signer_for_vote(clock) != Some(*vote_authority) {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    check_not_paused(vote_state)?;
+    check_has_node_identity(vote_state)?;
+
+    check_notarize_not_finalized(vote_state, vote_slot, cfg!(feature = "strict-protocol"))?;
+
+    let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
     award_notarization_credits(
         vote_state,
         vote_slot,
         vote.replayed_bank_hash,
         clock,
         slot_hashes,
-    )
+        &mut remaining_credits,
+    )?;
+    increment_vote_count(&mut vote_state.notarize_count);
+    vote_state.set_last_vote(kind, vote_slot, vote.block_id);
+    Ok(())
 }
 
 pub(crate) fn process_finalization_vote(
@@ -196,16 +408,50 @@ pub(crate) fn process_finalization_vote(
     clock: &Clock,
     slot: &PodSlot,
 ) -> Result<(), ProgramError> {
-    let mut vote_state = vote_account.data.borrow_mut();
-    let vote_state = bytemuck::from_bytes_mut::<VoteState>(&mut vote_state);
+    process_finalization_votes(vote_account, vote_authority, clock, slice::from_ref(slot))
+}
+
+/// Record a finalization vote on each of `slots`, awarding at most
+/// `MAX_CREDITS_PER_TRANSACTION` credits between them
+fn process_finalization_votes(
+    vote_account: &AccountInfo,
+    vote_authority: &Pubkey,
+    clock: &Clock,
+    slots: &[PodSlot],
+) -> Result<(), ProgramError> {
+    let mut data = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut data)?;
 
-    if vote_state.authorized_voter.voter != *vote_authority {
+    vote_state.promote_next_authorized_voter(clock.epoch);
+    if vote_state.signer_for_vote(clock) != Some(*vote_authority) {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let vote_slot = Slot::from(*slot);
+    check_not_paused(vote_state)?;
+    check_has_node_identity(vote_state)?;
+
+    let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
+    for slot in slots {
+        let vote_slot = Slot::from(*slot);
+
+        award_finalization_credits(vote_state, vote_slot, clock, &mut remaining_credits)?;
+        increment_vote_count(&mut vote_state.finalize_count);
+        if vote_slot > vote_state.last_finalized_slot() {
+            vote_state.last_finalized_slot = PodSlot::from(vote_slot);
+        }
+        vote_state.set_last_vote(VoteInstruction::Finalize, vote_slot, Hash::default());
+    }
+    Ok(())
+}
 
-    award_finalization_credits(vote_state, vote_slot, clock)
+pub(crate) fn process_finalize_many(
+    vote_account: &AccountInfo,
+    vote_authority: &Pubkey,
+    clock: &Clock,
+    slots: &[PodSlot],
+) -> Result<(), ProgramError> {
+    check_vote_many_slots(slots)?;
+    process_finalization_votes(vote_account, vote_authority, clock, slots)
 }
 
 pub(crate) fn process_skip_vote(
@@ -214,17 +460,26 @@ pub(crate) fn process_skip_vote(
     clock: &Clock,
     slot_hashes: &PodSlotHashes,
     slot: &PodSlot,
+    kind: VoteInstruction,
 ) -> Result<(), ProgramError> {
-    let mut vote_state = vote_account.data.borrow_mut();
-    let vote_state = bytemuck::from_bytes_mut::<VoteState>(&mut vote_state);
+    let mut data = vote_account.try_borrow_mut_data()?;
+    let vote_state = VoteState::deserialize_from_account_mut(&mut data)?;
 
-    if vote_state.authorized_voter.voter != *vote_authority {
+    vote_state.promote_next_authorized_voter(clock.epoch);
+    if vote_state.signer_for_vote(clock) != Some(*vote_authority) {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    check_not_paused(vote_state)?;
+    check_has_node_identity(vote_state)?;
+
     let slot = Slot::from(*slot);
 
-    award_skip_credits(vote_state, slot, clock, slot_hashes)
+    let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
+    award_skip_credits(vote_state, slot, clock, slot_hashes, &mut remaining_credits)?;
+    increment_vote_count(&mut vote_state.skip_count);
+    vote_state.set_last_vote(kind, slot, Hash::default());
+    Ok(())
 }
 
 #[cfg(test)]
@@ -234,21 +489,34 @@ mod tests {
     use solana_sdk::entrypoint::SUCCESS;
     use solana_sdk::epoch_schedule::EpochSchedule;
     use solana_sdk::hash::Hash;
+    use solana_sdk::program_error::ProgramError;
     use solana_sdk::program_stubs::{set_syscall_stubs, SyscallStubs};
     use solana_sdk::slot_hashes::SlotHashes;
     use solana_sdk::sysvar::slot_hashes::PodSlotHashes;
     use solana_sdk::sysvar::Sysvar;
     use solana_sdk::{clock::Clock, pubkey::Pubkey};
+    use spl_pod::optional_keys::OptionalNonZeroPubkey;
     use spl_pod::primitives::PodU64;
     use test_case::test_case;
 
-    use crate::accounting::EpochCredit;
-    use crate::vote_processor::{award_credits, set_credits};
+    use crate::accounting::{AuthorizedVoter, EpochCredit};
+    use crate::error::VoteError;
+    use crate::test_utils::TestAccount;
+    use crate::vote::Vote;
+    use crate::vote_processor::{
+        award_credits, award_notarization_credits, check_notarize_not_finalized, check_skip_range,
+        check_slot_hashes_fresh, compute_vote_latency, credit_loss, credits_for_latency_table,
+        is_slot_votable, process_finalization_vote, process_finalize_many,
+        process_notarization_vote, process_skip_vote, set_credits, skippable_slots,
+        NotarizationVoteInstructionData, CURRENT_NOTARIZE_VOTE_VERSION, MAX_SKIP_RANGE,
+        MAX_SLOT_HASHES_AGE,
+    };
     use crate::{
-        instruction::InitializeAccountInstructionData,
+        instruction::{InitializeAccountInstructionData, VoteInstruction},
         state::VoteState,
         vote_processor::{
-            latency_to_credits, VOTE_CREDITS_GRACE_SLOTS, VOTE_CREDITS_MAXIMUM_PER_SLOT,
+            latency_to_credits, MAX_CREDITS_PER_TRANSACTION, VOTE_CREDITS_CAP,
+            VOTE_CREDITS_GRACE_SLOTS, VOTE_CREDITS_MAXIMUM_PER_SLOT,
         },
     };
 
@@ -266,6 +534,63 @@ mod tests {
         );
     }
 
+    /// `(latency, program credits, legacy vote program credits)` for every
+    /// latency a vote can record, to compare the two credit curves side by side
+    fn credit_curve_report() -> Vec<(u64, u64, u64)> {
+        use solana_sdk::vote::state::{LandedVote, Lockout, VoteState as LegacyVoteState};
+
+        (0..=u8::MAX)
+            .map(|latency| {
+                let mut legacy = LegacyVoteState::default();
+                legacy.votes.push_back(LandedVote {
+                    latency,
+                    lockout: Lockout::new(0),
+                });
+                let latency = u64::from(latency);
+                (
+                    latency,
+                    latency_to_credits(latency),
+                    legacy.credits_for_vote_at_index(0),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_credit_curve_divergence() {
+        // The grace period and maximum are deliberately kept equal to the legacy
+        // vote program's, see `test_parity_old_vote_program`
+        assert_eq!(2, VOTE_CREDITS_GRACE_SLOTS);
+        assert_eq!(16, VOTE_CREDITS_MAXIMUM_PER_SLOT);
+
+        // The curves only differ at latency 0, which the legacy program reserves
+        // for votes recorded before latencies were tracked and awards 1 credit.
+        // Here it is a vote landing in the slot it votes on and earns the maximum
+        let divergent: Vec<_> = credit_curve_report()
+            .into_iter()
+            .filter(|(_, credits, legacy_credits)| credits != legacy_credits)
+            .collect();
+        assert_eq!(vec![(0, VOTE_CREDITS_MAXIMUM_PER_SLOT, 1)], divergent);
+    }
+
+    #[test]
+    fn test_compute_vote_latency() {
+        assert_eq!(0, compute_vote_latency(100, 100));
+        assert_eq!(0, compute_vote_latency(101, 100));
+        assert_eq!(3, compute_vote_latency(97, 100));
+        assert_eq!(255, compute_vote_latency(0, u64::MAX));
+
+        for (voted_for_slot, current_slot) in [(100, 100), (97, 100), (0, 300), (0, u64::MAX)] {
+            assert_eq!(
+                u64::from(solana_sdk::vote::state::VoteState::compute_vote_latency(
+                    voted_for_slot,
+                    current_slot
+                )),
+                compute_vote_latency(voted_for_slot, current_slot)
+            );
+        }
+    }
+
     #[test]
     fn test_latency_to_credits_max_credits() {
         for latency in 0..=VOTE_CREDITS_GRACE_SLOTS {
@@ -290,6 +615,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_credits_for_latency_table() {
+        let table = credits_for_latency_table();
+        for (index, (latency, credits)) in table.iter().enumerate() {
+            assert_eq!(index as u64, *latency);
+            assert_eq!(latency_to_credits(*latency), *credits);
+            assert_eq!(
+                VOTE_CREDITS_MAXIMUM_PER_SLOT - credits,
+                credit_loss(*latency)
+            );
+        }
+
+        // The table ends at the floor
+        let (last_latency, last_credits) = *table.last().unwrap();
+        assert_eq!(1, last_credits);
+        assert_eq!(2, latency_to_credits(last_latency - 1));
+        assert_eq!(0, credit_loss(0));
+        assert_eq!(VOTE_CREDITS_MAXIMUM_PER_SLOT - 1, credit_loss(100_000));
+    }
+
     fn setup_vote_state(clock: &Clock) -> VoteState {
         let bls_keypair = BlsKeypair::new();
         let bls_pubkey = bls_keypair.public.into();
@@ -299,6 +644,7 @@ mod tests {
             authorized_withdrawer: Pubkey::new_unique(),
             commission: 0_u8,
             bls_pubkey,
+            pool_marker: OptionalNonZeroPubkey::default(),
         };
 
         VoteState::new(&iaid, clock)
@@ -311,6 +657,14 @@ mod tests {
             .saturating_add(524_256)
     }
 
+    #[test]
+    fn test_credits_awarded_event() {
+        assert_eq!(
+            "event:credits_awarded slot=2048 epoch=4 credits=16",
+            super::credits_awarded_event(2048, 4, 16)
+        );
+    }
+
     // NOTE tests that use this mock MUST carry the #[serial] attribute
     struct MockGetSysvarSyscall {
         data: Vec<u8>,
@@ -463,11 +817,13 @@ mod tests {
         assert_eq!(0, vote_state.epoch_credits().credits());
         assert_eq!(0, vote_state.epoch_credits().prev_credits());
 
+        let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
         assert!(award_skip_credits(
             &mut vote_state,
             clock.slot - 5,
             &clock,
             &mock_slot_hash_entries(vec![]),
+            &mut remaining_credits,
         )
         .is_ok());
 
@@ -500,7 +856,8 @@ mod tests {
 
         let vote_slot = clock.slot.checked_sub(latency).unwrap();
 
-        assert!(award_credits(&mut vote_state, vote_slot, &clock).is_ok());
+        let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
+        assert!(award_credits(&mut vote_state, vote_slot, &clock, &mut remaining_credits).is_ok());
 
         let expected_awarded_credits = latency_to_credits(latency);
 
@@ -510,4 +867,589 @@ mod tests {
         );
         assert_eq!(0, vote_state.epoch_credits().prev_credits());
     }
+
+    #[test]
+    #[serial]
+    fn test_process_votes_increment_counters() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let vote_state = setup_vote_state(&clock);
+        let authority = *vote_state.authorized_voter().voter();
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+
+        let notarized_slots = [clock.slot - 3, clock.slot - 2, clock.slot - 1];
+        let slot_hash_entries: Vec<(u64, Hash)> = notarized_slots
+            .iter()
+            .map(|slot| (*slot, Hash::new_unique()))
+            .collect();
+        let slot_hashes = mock_slot_hash_entries(slot_hash_entries.clone());
+
+        for (slot, bank_hash) in slot_hash_entries {
+            let vote = NotarizationVoteInstructionData {
+                version: CURRENT_NOTARIZE_VOTE_VERSION,
+                slot: PodU64::from(slot),
+                block_id: Hash::new_unique(),
+                _replayed_slot: PodU64::from(0),
+                replayed_bank_hash: bank_hash,
+            };
+            process_notarization_vote(
+                &vote_account,
+                &authority,
+                &clock,
+                &slot_hashes,
+                &vote,
+                VoteInstruction::Notarize,
+            )
+            .unwrap();
+        }
+        for slot in notarized_slots.iter().take(2) {
+            process_finalization_vote(&vote_account, &authority, &clock, &PodU64::from(*slot))
+                .unwrap();
+        }
+        for slot in [
+            clock.slot - 10,
+            clock.slot - 9,
+            clock.slot - 8,
+            clock.slot - 7,
+        ] {
+            process_skip_vote(
+                &vote_account,
+                &authority,
+                &clock,
+                &slot_hashes,
+                &PodU64::from(slot),
+                VoteInstruction::SkipFallback,
+            )
+            .unwrap();
+        }
+        // Votes that fail do not count
+        assert!(process_skip_vote(
+            &vote_account,
+            &authority,
+            &clock,
+            &slot_hashes,
+            &PodU64::from(notarized_slots[0]),
+            VoteInstruction::Skip,
+        )
+        .is_err());
+
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(3, vote_state.notarize_count());
+        assert_eq!(2, vote_state.finalize_count());
+        assert_eq!(4, vote_state.skip_count());
+        // The last vote landed is recorded under the kind it was cast as
+        assert!(vote_state.matches_last_vote(&Vote::new_skip_fallback_vote(clock.slot - 7)));
+        assert!(!vote_state.matches_last_vote(&Vote::new_skip_vote(clock.slot - 7)));
+    }
+
+    #[test]
+    #[serial]
+    fn test_award_notarization_credits_bank_hash_checks() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let mut vote_state = setup_vote_state(&clock);
+        let vote_slot = clock.slot - 1;
+        let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
+
+        // A zero bank hash is rejected even if the slot hashes entry is also zero
+        let slot_hashes = mock_slot_hash_entries(vec![(vote_slot, Hash::default())]);
+        assert_eq!(
+            Err(VoteError::ReplayBankHashMismatch.into()),
+            award_notarization_credits(
+                &mut vote_state,
+                vote_slot,
+                Hash::default(),
+                &clock,
+                &slot_hashes,
+                &mut remaining_credits,
+            )
+        );
+
+        let bank_hash = Hash::new_unique();
+        let slot_hashes = mock_slot_hash_entries(vec![(vote_slot, bank_hash)]);
+        assert_eq!(
+            Err(VoteError::ReplayBankHashMismatch.into()),
+            award_notarization_credits(
+                &mut vote_state,
+                vote_slot,
+                Hash::new_unique(),
+                &clock,
+                &slot_hashes,
+                &mut remaining_credits,
+            )
+        );
+        assert_eq!(0, vote_state.epoch_credits().credits());
+
+        assert_eq!(
+            Ok(()),
+            award_notarization_credits(
+                &mut vote_state,
+                vote_slot,
+                bank_hash,
+                &clock,
+                &slot_hashes,
+                &mut remaining_credits,
+            )
+        );
+        assert_eq!(latency_to_credits(1), vote_state.epoch_credits().credits());
+    }
+
+    #[test]
+    #[serial]
+    fn test_check_slot_hashes_fresh() {
+        let clock_slot = epoch_to_starting_slot(256);
+
+        let fresh = mock_slot_hash_entries(vec![
+            (clock_slot - 1, Hash::new_unique()),
+            (clock_slot - 2, Hash::new_unique()),
+        ]);
+        assert_eq!(Ok(()), check_slot_hashes_fresh(&fresh, clock_slot, true));
+
+        let oldest_fresh =
+            mock_slot_hash_entries(vec![(clock_slot - MAX_SLOT_HASHES_AGE, Hash::new_unique())]);
+        assert_eq!(
+            Ok(()),
+            check_slot_hashes_fresh(&oldest_fresh, clock_slot, true)
+        );
+
+        let stale = mock_slot_hash_entries(vec![
+            (clock_slot - MAX_SLOT_HASHES_AGE - 1, Hash::new_unique()),
+            (clock_slot - MAX_SLOT_HASHES_AGE - 2, Hash::new_unique()),
+        ]);
+        assert_eq!(
+            Err(VoteError::StaleSlotHashes.into()),
+            check_slot_hashes_fresh(&stale, clock_slot, true)
+        );
+        assert_eq!(Ok(()), check_slot_hashes_fresh(&stale, clock_slot, false));
+
+        let empty = mock_slot_hash_entries(vec![]);
+        assert_eq!(
+            Err(VoteError::StaleSlotHashes.into()),
+            check_slot_hashes_fresh(&empty, clock_slot, true)
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_is_slot_votable() {
+        let clock_slot = epoch_to_starting_slot(256);
+        let slot_hashes = mock_slot_hash_entries(vec![
+            (clock_slot - 1, Hash::new_unique()),
+            (clock_slot - 3, Hash::new_unique()),
+        ]);
+
+        // Slots in the window with a hash are votable
+        assert!(is_slot_votable(clock_slot - 1, &slot_hashes));
+        assert!(is_slot_votable(clock_slot - 3, &slot_hashes));
+
+        // Skipped, not yet replayed and pruned slots are not
+        assert!(!is_slot_votable(clock_slot - 2, &slot_hashes));
+        assert!(!is_slot_votable(clock_slot, &slot_hashes));
+        assert!(!is_slot_votable(clock_slot - 4, &slot_hashes));
+    }
+
+    #[test]
+    #[serial]
+    fn test_award_notarization_credits_missing_slot() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let mut vote_state = setup_vote_state(&clock);
+        let newest = clock.slot - 2;
+        let oldest = clock.slot - 10;
+        let slot_hashes = mock_slot_hash_entries(vec![
+            (newest, Hash::new_unique()),
+            (clock.slot - 5, Hash::new_unique()),
+            (oldest, Hash::new_unique()),
+        ]);
+        let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
+
+        for (vote_slot, expected) in [
+            (newest + 1, VoteError::NotarizeSlotNotReplayed),
+            (oldest - 1, VoteError::NotarizeSlotTooOld),
+            (clock.slot - 4, VoteError::SlotHashesMissingKey),
+        ] {
+            assert_eq!(
+                Err(expected.into()),
+                award_notarization_credits(
+                    &mut vote_state,
+                    vote_slot,
+                    Hash::new_unique(),
+                    &clock,
+                    &slot_hashes,
+                    &mut remaining_credits,
+                )
+            );
+        }
+        assert_eq!(0, vote_state.epoch_credits().credits());
+    }
+
+    #[test]
+    fn test_set_credits_overflow() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let mut vote_state = setup_vote_state(&clock);
+
+        let credits = VOTE_CREDITS_CAP - VOTE_CREDITS_MAXIMUM_PER_SLOT;
+        vote_state.epoch_credits = EpochCredit::new(256, credits, 0);
+        assert_eq!(
+            Ok(()),
+            set_credits(&mut vote_state, 256, VOTE_CREDITS_MAXIMUM_PER_SLOT)
+        );
+        assert_eq!(VOTE_CREDITS_CAP, vote_state.epoch_credits().credits());
+
+        assert_eq!(
+            Err(VoteError::CreditsOverflow.into()),
+            set_credits(&mut vote_state, 256, 1)
+        );
+        assert_eq!(VOTE_CREDITS_CAP, vote_state.epoch_credits().credits());
+
+        // Rolling over into a new epoch must also respect the cap
+        vote_state.epoch_credits = EpochCredit::new(256, credits, 0);
+        assert_eq!(
+            Err(VoteError::CreditsOverflow.into()),
+            set_credits(&mut vote_state, 257, VOTE_CREDITS_MAXIMUM_PER_SLOT + 1)
+        );
+        assert_eq!(EpochCredit::new(256, credits, 0), vote_state.epoch_credits);
+    }
+
+    #[test]
+    #[serial]
+    fn test_check_notarize_not_finalized() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let vote_state = setup_vote_state(&clock);
+        let authority = *vote_state.authorized_voter().voter();
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+
+        let finalized_slot = clock.slot - 5;
+        process_finalization_vote(
+            &vote_account,
+            &authority,
+            &clock,
+            &PodU64::from(finalized_slot),
+        )
+        .unwrap();
+        // An older finalization does not move the last finalized slot back
+        process_finalization_vote(
+            &vote_account,
+            &authority,
+            &clock,
+            &PodU64::from(finalized_slot - 1),
+        )
+        .unwrap();
+
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(finalized_slot, vote_state.last_finalized_slot());
+
+        for (slot, strict_result) in [
+            (
+                finalized_slot - 1,
+                Err(VoteError::NotarizeFinalizedSlot.into()),
+            ),
+            (finalized_slot, Err(VoteError::NotarizeFinalizedSlot.into())),
+            (finalized_slot + 1, Ok(())),
+        ] {
+            assert_eq!(
+                strict_result,
+                check_notarize_not_finalized(vote_state, slot, true)
+            );
+            assert_eq!(
+                Ok(()),
+                check_notarize_not_finalized(vote_state, slot, false)
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_finalize_many() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let vote_state = setup_vote_state(&clock);
+        let authority = *vote_state.authorized_voter().voter();
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+
+        let latencies = [20, 10, 5, 3, 1];
+        let slots: Vec<PodU64> = latencies
+            .iter()
+            .map(|latency| PodU64::from(clock.slot - latency))
+            .collect();
+        process_finalize_many(&vote_account, &authority, &clock, &slots).unwrap();
+
+        let expected_credits: u64 = latencies.iter().map(|l| latency_to_credits(*l)).sum();
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(expected_credits, vote_state.epoch_credits().credits());
+        assert_eq!(5, vote_state.finalize_count());
+        assert_eq!(clock.slot - 1, vote_state.last_finalized_slot());
+        drop(data);
+
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            process_finalize_many(&vote_account, &Pubkey::new_unique(), &clock, &slots)
+        );
+
+        // Unsorted and duplicate slots are rejected before any vote is recorded
+        let slot = |latency: u64| PodU64::from(clock.slot - latency);
+        for slots in [[slot(1), slot(2)], [slot(2), slot(2)]] {
+            let before = vote_account.try_borrow_data().unwrap().to_vec();
+            assert_eq!(
+                Err(ProgramError::InvalidInstructionData),
+                process_finalize_many(&vote_account, &authority, &clock, &slots)
+            );
+            assert_eq!(before, *vote_account.try_borrow_data().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_process_finalize_many_credits_cap() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let vote_state = setup_vote_state(&clock);
+        let authority = *vote_state.authorized_voter().voter();
+
+        // Votes that land without latency earn the maximum, so one vote past
+        // the cap is rejected
+        let votes_at_cap = MAX_CREDITS_PER_TRANSACTION / VOTE_CREDITS_MAXIMUM_PER_SLOT;
+        let slots: Vec<PodU64> = (clock.slot..=clock.slot + votes_at_cap)
+            .map(PodU64::from)
+            .collect();
+        let mut account = TestAccount::new(&vote_state);
+        assert_eq!(
+            Err(VoteError::CreditsPerTxExceeded.into()),
+            process_finalize_many(&account.info(), &authority, &clock, &slots)
+        );
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        process_finalize_many(&vote_account, &authority, &clock, &slots[1..]).unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(
+            MAX_CREDITS_PER_TRANSACTION,
+            vote_state.epoch_credits().credits()
+        );
+        drop(data);
+
+        // The cap applies to every award, not only to batches
+        let mut vote_state = setup_vote_state(&clock);
+        let mut remaining_credits = VOTE_CREDITS_MAXIMUM_PER_SLOT - 1;
+        assert_eq!(
+            Err(VoteError::CreditsPerTxExceeded.into()),
+            award_credits(&mut vote_state, clock.slot, &clock, &mut remaining_credits)
+        );
+        assert_eq!(VOTE_CREDITS_MAXIMUM_PER_SLOT - 1, remaining_credits);
+        assert_eq!(0, vote_state.epoch_credits().credits());
+    }
+
+    #[test]
+    fn test_vote_signer_at_voter_change() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let mut vote_state = setup_vote_state(&clock);
+        let voter = *vote_state.authorized_voter().voter();
+        let next_voter = Pubkey::new_unique();
+        vote_state
+            .set_next_authorized_voter(AuthorizedVoter::new(257, next_voter))
+            .unwrap();
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+
+        // The last slot before the change is still signed by the current voter
+        let last_clock = Clock {
+            slot: epoch_to_starting_slot(257) - 1,
+            ..clock
+        };
+        let slot = PodU64::from(last_clock.slot - 1);
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            process_finalization_vote(&vote_account, &next_voter, &last_clock, &slot)
+        );
+        process_finalization_vote(&vote_account, &voter, &last_clock, &slot).unwrap();
+
+        // From the first slot of the new epoch, only the next voter can sign
+        let next_clock = Clock {
+            slot: epoch_to_starting_slot(257),
+            epoch: 257,
+            ..clock
+        };
+        let slot = PodU64::from(next_clock.slot - 1);
+        assert_eq!(
+            Err(ProgramError::MissingRequiredSignature),
+            process_finalization_vote(&vote_account, &voter, &next_clock, &slot)
+        );
+        process_finalization_vote(&vote_account, &next_voter, &next_clock, &slot).unwrap();
+
+        // The vote made the next voter current
+        let data = vote_account.try_borrow_data().unwrap();
+        let vote_state = VoteState::deserialize(&data).unwrap();
+        assert_eq!(2, vote_state.finalize_count());
+        assert_eq!(&next_voter, vote_state.authorized_voter().voter());
+        assert_eq!(None, vote_state.next_authorized_voter());
+    }
+
+    #[test]
+    fn test_vote_without_node_identity() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let mut vote_state = setup_vote_state(&clock);
+        vote_state.node_pubkey = Pubkey::default();
+        let authority = *vote_state.authorized_voter().voter();
+
+        let mut account = TestAccount::new(&vote_state);
+        let vote_account = account.info();
+        let slot = PodU64::from(clock.slot - 1);
+
+        assert_eq!(
+            Err(VoteError::MissingNodeIdentity.into()),
+            process_finalization_vote(&vote_account, &authority, &clock, &slot)
+        );
+        let data = vote_account.try_borrow_data().unwrap();
+        assert_eq!(0, VoteState::deserialize(&data).unwrap().finalize_count());
+        drop(data);
+
+        // Once a node identity is set the account can vote
+        let mut data = vote_account.try_borrow_mut_data().unwrap();
+        bytemuck::from_bytes_mut::<VoteState>(&mut data).node_pubkey = Pubkey::new_unique();
+        drop(data);
+        process_finalization_vote(&vote_account, &authority, &clock, &slot).unwrap();
+        let data = vote_account.try_borrow_data().unwrap();
+        assert_eq!(1, VoteState::deserialize(&data).unwrap().finalize_count());
+    }
+
+    #[test]
+    #[serial]
+    fn test_skippable_slots() {
+        let clock_slot = 100;
+        let slot_hashes = mock_slot_hash_entries(
+            [90, 92, 93, 97]
+                .into_iter()
+                .map(|slot| (slot, Hash::new_unique()))
+                .collect(),
+        );
+
+        assert_eq!(
+            Ok(vec![91, 94, 95, 96, 98, 99]),
+            skippable_slots(90..=105, &slot_hashes, clock_slot)
+        );
+        assert_eq!(
+            Ok(vec![94, 95]),
+            skippable_slots(92..=95, &slot_hashes, clock_slot)
+        );
+        assert_eq!(
+            Ok(vec![]),
+            skippable_slots(92..=93, &slot_hashes, clock_slot)
+        );
+        assert_eq!(
+            Ok(vec![]),
+            skippable_slots(100..=110, &slot_hashes, clock_slot)
+        );
+        assert_eq!(
+            Err(VoteError::SkipRangeTooLarge),
+            skippable_slots(0..=MAX_SKIP_RANGE, &slot_hashes, clock_slot)
+        );
+    }
+
+    #[test]
+    fn test_vote_rejects_older_accounts() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256),
+            epoch: 256,
+            ..Clock::default()
+        };
+        let vote_state = setup_vote_state(&clock);
+        let authority = *vote_state.authorized_voter().voter();
+        let slot = PodU64::from(clock.slot - 1);
+
+        // Accounts allocated for an older, smaller layout, and older layouts in
+        // an account large enough for the current one, must be migrated first
+        let mut older_version = bytemuck::bytes_of(&vote_state).to_vec();
+        older_version[0] -= 1;
+        for data in [
+            bytemuck::bytes_of(&vote_state)[..VoteState::size() - 1].to_vec(),
+            older_version,
+        ] {
+            let mut account = TestAccount::with_data(data);
+            assert_eq!(
+                Err(ProgramError::InvalidAccountData),
+                process_finalization_vote(&account.info(), &authority, &clock, &slot)
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_skip_range() {
+        assert_eq!(Ok(()), check_skip_range(&(5..=5)));
+        // Exactly `MAX_SKIP_RANGE` slots
+        assert_eq!(Ok(()), check_skip_range(&(10..=10 + MAX_SKIP_RANGE - 1)));
+        // One over
+        assert_eq!(
+            Err(VoteError::SkipRangeTooLarge),
+            check_skip_range(&(10..=10 + MAX_SKIP_RANGE))
+        );
+        assert_eq!(
+            Err(VoteError::SkipRangeTooLarge),
+            check_skip_range(&(0..=u64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_last_credited_epoch() {
+        let clock = Clock {
+            slot: epoch_to_starting_slot(256) + 2,
+            epoch: 256,
+            ..Clock::default()
+        };
+        let mut vote_state = setup_vote_state(&clock);
+        assert_eq!(0, vote_state.last_credited_epoch());
+
+        let mut remaining_credits = MAX_CREDITS_PER_TRANSACTION;
+        award_credits(
+            &mut vote_state,
+            clock.slot - 1,
+            &clock,
+            &mut remaining_credits,
+        )
+        .unwrap();
+        assert_eq!(256, vote_state.last_credited_epoch());
+        assert_eq!(
+            vote_state.epoch_credits().epoch(),
+            vote_state.last_credited_epoch()
+        );
+    }
 }
@@ -3,20 +3,24 @@
 use {
     alpenglow_vote::{
         accounting::EpochCredit,
+        error::VoteError,
+        governance,
         instruction::{self, AuthorityType, InitializeAccountInstructionData},
-        state::VoteState,
+        state::{CommissionConfig, VoteState},
+        vote::{FinalizationVote, Vote},
     },
-    mollusk_svm::Mollusk,
+    mollusk_svm::{program::keyed_account_for_system_program, Mollusk},
     rand::Rng,
     solana_bls_signatures::{keypair::Keypair as BlsKeypair, Pubkey as BlsPubkey},
-    solana_program::pubkey::Pubkey,
+    solana_loader_v3_interface::state::UpgradeableLoaderState,
+    solana_program::{bpf_loader_upgradeable, pubkey::Pubkey, system_program},
     solana_sdk::{
         account::Account,
         clock::{Epoch, Slot},
-        instruction::Instruction,
+        instruction::{AccountMeta, Instruction, InstructionError},
         signature::{Keypair, Signer},
     },
-    spl_pod::bytemuck::pod_from_bytes,
+    spl_pod::{bytemuck::pod_from_bytes, optional_keys::OptionalNonZeroPubkey},
 };
 
 const SLOT: Slot = 53_084_024;
@@ -38,6 +42,7 @@ fn initialize_vote_account_mollusk(
             authorized_withdrawer: *authorized_withdrawer,
             commission,
             bls_pubkey: *bls_pubkey,
+            pool_marker: OptionalNonZeroPubkey::default(),
         },
     )
 }
@@ -47,6 +52,7 @@ fn setup_clock_mollusk(mollusk: &mut Mollusk, slot: Option<Slot>) {
     let clock = &mut mollusk.sysvars.clock;
     clock.slot = slot.unwrap_or(SLOT);
     clock.epoch = EPOCH;
+    clock.leader_schedule_epoch = EPOCH + 1;
 }
 
 fn build_mollusk() -> Mollusk {
@@ -79,6 +85,112 @@ fn build_empty_vote_account_with_excess_lamports(
     )
 }
 
+/// A vote account holding `vote_state` in the smaller layout of the previous
+/// vote state version
+fn build_previous_version_vote_account(vote_state: &VoteState, lamports: u64) -> Account {
+    let version = VoteState::SUPPORTED_VERSIONS[VoteState::SUPPORTED_VERSIONS.len() - 2];
+    let size = VoteState::size_for_version(version).unwrap();
+    let mut data = bytemuck::bytes_of(vote_state)[..size].to_vec();
+    data[0] = version;
+    Account {
+        lamports,
+        data,
+        owner: alpenglow_vote::id(),
+        ..Account::default()
+    }
+}
+
+/// The `ProgramData` account of the vote program, recording
+/// `upgrade_authority_address` as the governance authority
+fn build_program_data_account(upgrade_authority_address: Option<Pubkey>) -> Account {
+    let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address,
+    })
+    .unwrap();
+    data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+    Account {
+        lamports: 1,
+        data,
+        owner: bpf_loader_upgradeable::id(),
+        ..Account::default()
+    }
+}
+
+#[test]
+fn test_initialize_vote_account_pda() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let node_key = Keypair::new();
+    let (vote_pubkey, bump) = instruction::derive_vote_account(&node_key.pubkey());
+    assert_eq!(
+        (vote_pubkey, bump),
+        instruction::derive_vote_account(&node_key.pubkey())
+    );
+
+    let instruction = instruction::initialize_account(
+        vote_pubkey,
+        &InitializeAccountInstructionData {
+            node_pubkey: node_key.pubkey(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 0,
+            bls_pubkey: BlsKeypair::new().public.into(),
+            pool_marker: OptionalNonZeroPubkey::default(),
+        },
+    );
+
+    let result = mollusk.process_instruction(
+        &instruction,
+        &[
+            (vote_pubkey, build_empty_vote_account(&mollusk)),
+            (node_key.pubkey(), Account::default()),
+        ],
+    );
+    assert!(result.raw_result.is_ok());
+
+    let vote_account = result.get_account(&vote_pubkey).unwrap();
+    assert_eq!(alpenglow_vote::id(), vote_account.owner);
+    let vote_state: &VoteState = pod_from_bytes(&vote_account.data).unwrap();
+    assert!(vote_state.is_initialized());
+    assert_eq!(node_key.pubkey(), *vote_state.node_pubkey());
+}
+
+#[test]
+fn test_initialize_vote_account_node_signer_mismatch() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let other_signer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+
+    let mut instruction = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        0,
+        &bls_keypair.public.into(),
+    );
+    instruction.accounts[1].pubkey = other_signer.pubkey();
+
+    let result = mollusk.process_instruction(
+        &instruction,
+        &[
+            (other_signer.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        ],
+    );
+
+    assert_eq!(
+        Err(InstructionError::Custom(
+            VoteError::NodeIdentityMustSign as u32
+        )),
+        result.raw_result
+    );
+}
+
 #[test]
 fn test_initialize_vote_account_basic() {
     let mollusk = build_mollusk_with_clock(None);
@@ -114,7 +226,7 @@ fn test_initialize_vote_account_basic() {
 
     let vote_state: &VoteState = pod_from_bytes(&vote_account.data).unwrap();
 
-    assert_eq!(1, vote_state.version());
+    assert_eq!(9, vote_state.version());
     assert_eq!(node_key.pubkey(), *vote_state.node_pubkey());
     assert_eq!(
         authorized_withdrawer.pubkey(),
@@ -128,6 +240,11 @@ fn test_initialize_vote_account_basic() {
     assert_eq!(EPOCH, vote_state.authorized_voter().epoch());
     assert_eq!(None, vote_state.next_authorized_voter());
     assert_eq!(EpochCredit::default(), *vote_state.epoch_credits());
+    assert_eq!(None, vote_state.collector_authority());
+    assert_eq!(None, vote_state.pool_marker());
+    assert_eq!(0, vote_state.last_finalized_slot());
+    assert!(!vote_state.paused());
+    assert_eq!(None, vote_state.rank());
 }
 
 #[test]
@@ -196,6 +313,134 @@ fn test_authorize_voter_basic() {
     );
 }
 
+#[test]
+fn test_authorize_voter_default_pubkey() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Authorizing the default pubkey as voter would lock the account out of voting
+    let authorize_ixn = instruction::authorize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        Pubkey::default(),
+        AuthorityType::Voter,
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, authorize_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_voter.pubkey(), Account::default()),
+        ],
+    );
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(VoteError::InvalidAuthority as u32))
+    );
+}
+
+#[test]
+fn test_authorize_voter_overwrite_pending() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    let first_voter = Keypair::new();
+    let second_voter = Keypair::new();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    let first_authorize_ixn = instruction::authorize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        first_voter.pubkey(),
+        AuthorityType::Voter,
+    );
+    let accounts = [
+        (node_key.pubkey(), Account::default()),
+        (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        (authorized_voter.pubkey(), Account::default()),
+    ];
+
+    // Without force the pending voter cannot be overwritten in the same epoch
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn.clone(),
+            first_authorize_ixn.clone(),
+            instruction::authorize(
+                vote_account.pubkey(),
+                authorized_voter.pubkey(),
+                second_voter.pubkey(),
+                AuthorityType::Voter,
+            ),
+        ],
+        &accounts,
+    );
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(
+            VoteError::PendingVoterOverwrite as u32
+        ))
+    );
+
+    // With force it can
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn,
+            first_authorize_ixn,
+            instruction::force_authorize(
+                vote_account.pubkey(),
+                authorized_voter.pubkey(),
+                second_voter.pubkey(),
+                AuthorityType::Voter,
+            ),
+        ],
+        &accounts,
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_account = result.get_account(&vote_account.pubkey()).unwrap();
+    let vote_state: &VoteState = pod_from_bytes(&vote_account.data).unwrap();
+
+    assert_eq!(
+        Some(second_voter.pubkey()),
+        vote_state.next_authorized_voter().map(|nav| *nav.voter()),
+    );
+}
+
 #[test]
 fn test_authorize_withdrawer_basic() {
     let mollusk = build_mollusk_with_clock(None);
@@ -390,6 +635,52 @@ fn test_authorize_checked_withdrawer_basic() {
     assert_eq!(new_authority.pubkey(), *vote_state.authorized_withdrawer());
 }
 
+#[test]
+fn test_authorize_checked_duplicate_authority() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Pass the current authority again as the new authority
+    let authorize_ixn = instruction::authorize_checked(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        authorized_withdrawer.pubkey(),
+        AuthorityType::Withdrawer,
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, authorize_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(
+            VoteError::DuplicateAuthorityAccount as u32
+        ))
+    );
+}
+
 #[test]
 fn test_authorize_with_seed_voter_basic() {
     let mollusk = build_mollusk_with_clock(None);
@@ -762,82 +1053,124 @@ fn test_update_commission_basic() {
         pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
 
     assert_eq!(commission_after, vote_state.commission());
+    assert_eq!(27, vote_state.last_commission_change());
 }
 
 #[test]
-fn test_update_validator_identity_basic() {
+fn test_set_commission_config() {
     let mollusk = build_mollusk_with_clock(None);
 
     let vote_account = Keypair::new();
-    let old_node = Keypair::new();
-    let authorized_voter = Keypair::new();
+    let node_key = Keypair::new();
     let authorized_withdrawer = Keypair::new();
-    let bls_keypair = BlsKeypair::new();
-    let bls_pubkey = bls_keypair.public.into();
-
-    // This (probably) won't fail (p is very low - if it fails, you probably win something)
-    let new_node = Keypair::new();
-    assert_ne!(old_node, new_node);
+    let governance_authority = Keypair::new();
+    let payer = Keypair::new();
+    let bls_pubkey = BlsKeypair::new().public.into();
+    let config_pubkey = instruction::derive_commission_config().0;
 
-    // Create a vote account
     let initialize_ixn = initialize_vote_account_mollusk(
         &vote_account,
-        &old_node,
-        &authorized_voter.pubkey(),
+        &node_key,
+        &Pubkey::new_unique(),
         &authorized_withdrawer.pubkey(),
-        42,
+        5,
         &bls_pubkey,
     );
-
-    let result = mollusk.process_instruction(
-        &initialize_ixn,
-        &[
-            (old_node.pubkey(), Account::default()),
-            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
-        ],
+    let set_config_ixn =
+        instruction::set_commission_config(governance_authority.pubkey(), payer.pubkey(), 10);
+    // Pass the config after the required accounts of `UpdateCommission`
+    let update_commission_ixn = |commission| {
+        let mut ixn = instruction::update_commission(
+            vote_account.pubkey(),
+            authorized_withdrawer.pubkey(),
+            commission,
+        );
+        ixn.accounts
+            .push(AccountMeta::new_readonly(config_pubkey, false));
+        ixn
+    };
+    let accounts = [
+        (node_key.pubkey(), Account::default()),
+        (config_pubkey, Account::default()),
+        (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        (authorized_withdrawer.pubkey(), Account::default()),
+        (governance_authority.pubkey(), Account::default()),
+        (
+            governance::program_data_address(),
+            build_program_data_account(Some(governance_authority.pubkey())),
+        ),
+        (
+            payer.pubkey(),
+            Account::new(1_000_000_000, 0, &system_program::id()),
+        ),
+        keyed_account_for_system_program(),
+    ];
+
+    // Commissions are not capped until governance creates the config
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn.clone(), update_commission_ixn(20)],
+        &accounts,
     );
 
     assert!(result.raw_result.is_ok());
 
-    let vote_state: &VoteState =
-        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
-
-    assert_eq!(old_node.pubkey(), *vote_state.node_pubkey());
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn.clone(),
+            set_config_ixn.clone(),
+            update_commission_ixn(20),
+        ],
+        &accounts,
+    );
 
-    // Issue an UpdateValidatorIdentity transaction
-    let update_vi_txn = instruction::update_validator_identity(
-        vote_account.pubkey(),
-        authorized_withdrawer.pubkey(),
-        new_node.pubkey(),
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(
+            VoteError::CommissionExceedsMaximum as u32
+        ))
     );
 
     let result = mollusk.process_instruction_chain(
-        &[initialize_ixn, update_vi_txn],
         &[
-            (old_node.pubkey(), Account::default()),
-            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
-            (authorized_withdrawer.pubkey(), Account::default()),
-            (new_node.pubkey(), Account::default()),
+            initialize_ixn.clone(),
+            set_config_ixn.clone(),
+            update_commission_ixn(10),
         ],
+        &accounts,
     );
 
     assert!(result.raw_result.is_ok());
 
+    let config_account = result.get_account(&config_pubkey).unwrap();
+    assert_eq!(alpenglow_vote::id(), config_account.owner);
+    assert_eq!(
+        CommissionConfig { max_commission: 10 },
+        *pod_from_bytes::<CommissionConfig>(&config_account.data).unwrap()
+    );
     let vote_state: &VoteState =
         pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+    assert_eq!(10, vote_state.commission());
 
-    assert_eq!(new_node.pubkey(), *vote_state.node_pubkey());
+    // Only the upgrade authority recorded in `ProgramData` can set the config
+    let set_config_ixn =
+        instruction::set_commission_config(authorized_withdrawer.pubkey(), payer.pubkey(), 10);
+    let result = mollusk.process_instruction_chain(&[initialize_ixn, set_config_ixn], &accounts);
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::MissingRequiredSignature)
+    );
 }
 
 #[test]
-fn test_withdraw_basic() {
+fn test_set_collector_authority_basic() {
     let mollusk = build_mollusk_with_clock(None);
 
     let vote_account = Keypair::new();
     let node_key = Keypair::new();
     let authorized_voter = Keypair::new();
     let authorized_withdrawer = Keypair::new();
-    let recipient_account = Keypair::new();
+    let collector = Keypair::new();
     let bls_keypair = BlsKeypair::new();
     let bls_pubkey = bls_keypair.public.into();
 
@@ -851,36 +1184,768 @@ fn test_withdraw_basic() {
         &bls_pubkey,
     );
 
-    let result = mollusk.process_instruction(
-        &initialize_ixn,
+    // Issue a SetCollectorAuthority transaction
+    let set_collector_ixn = instruction::set_collector_authority(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        Some(collector.pubkey()),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn.clone(), set_collector_ixn.clone()],
         &[
             (node_key.pubkey(), Account::default()),
-            (
-                vote_account.pubkey(),
-                build_empty_vote_account_with_excess_lamports(&mollusk, 1_234_567),
-            ),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_withdrawer.pubkey(), Account::default()),
         ],
     );
 
     assert!(result.raw_result.is_ok());
 
-    let account = result.get_account(&vote_account.pubkey()).unwrap();
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
 
-    let rent_exempt_amount = 3_027_600;
-    assert_eq!(rent_exempt_amount + 1_234_567, account.lamports);
+    assert_eq!(Some(collector.pubkey()), vote_state.collector_authority());
 
-    // Issue a Withdraw transaction
-    let withdraw_ixn = instruction::withdraw(
+    // Clear the collector
+    let clear_collector_ixn = instruction::set_collector_authority(
         vote_account.pubkey(),
         authorized_withdrawer.pubkey(),
-        1_234_567,
-        recipient_account.pubkey(),
+        None,
     );
 
     let result = mollusk.process_instruction_chain(
-        &[initialize_ixn, withdraw_ixn],
         &[
-            (node_key.pubkey(), Account::default()),
+            initialize_ixn.clone(),
+            set_collector_ixn,
+            clear_collector_ixn,
+        ],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+
+    assert_eq!(None, vote_state.collector_authority());
+
+    // The voter cannot set the collector
+    let set_collector_ixn = instruction::set_collector_authority(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        Some(collector.pubkey()),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, set_collector_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_voter.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_err());
+}
+
+#[test]
+fn test_assign_rank() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let governance_authority = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+    let accounts = [
+        (node_key.pubkey(), Account::default()),
+        (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        (governance_authority.pubkey(), Account::default()),
+        (
+            governance::program_data_address(),
+            build_program_data_account(Some(governance_authority.pubkey())),
+        ),
+        (authorized_withdrawer.pubkey(), Account::default()),
+    ];
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn.clone(),
+            instruction::assign_rank(vote_account.pubkey(), governance_authority.pubkey(), 7),
+        ],
+        &accounts,
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_account_data = result.get_account(&vote_account.pubkey()).unwrap();
+    let vote_state: &VoteState = pod_from_bytes(&vote_account_data.data).unwrap();
+
+    assert_eq!(Some(7), vote_state.rank());
+
+    // Only the upgrade authority recorded in `ProgramData` can assign ranks
+    let assign_rank_ixn =
+        instruction::assign_rank(vote_account.pubkey(), authorized_withdrawer.pubkey(), 7);
+    let result = mollusk.process_instruction_chain(&[initialize_ixn, assign_rank_ixn], &accounts);
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_deinitialize_idle_account() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account, it has never earned credits
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+    let empty_vote_account = build_empty_vote_account(&mollusk);
+    let lamports = empty_vote_account.lamports;
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn,
+            instruction::deinitialize(vote_account.pubkey(), authorized_withdrawer.pubkey()),
+        ],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), empty_vote_account),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    // The state is zeroed but the lamports remain
+    let vote_account = result.get_account(&vote_account.pubkey()).unwrap();
+    assert_eq!(lamports, vote_account.lamports);
+    assert!(vote_account.data.iter().all(|byte| *byte == 0));
+}
+
+#[test]
+fn test_deinitialize_active_account() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Earn credits in the current epoch
+    let finalize_ixn = instruction::finalize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        &FinalizationVote::new(SLOT - 1),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn,
+            finalize_ixn,
+            instruction::deinitialize(vote_account.pubkey(), authorized_withdrawer.pubkey()),
+        ],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_voter.pubkey(), Account::default()),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(
+            VoteError::ActiveVoteAccountClose as u32
+        ))
+    );
+}
+
+#[test]
+fn test_get_credits() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Earn credits in the current epoch
+    let finalize_ixn = instruction::finalize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        &FinalizationVote::new(SLOT - 1),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn,
+            finalize_ixn,
+            instruction::get_credits(vote_account.pubkey()),
+        ],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_voter.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_account = result.get_account(&vote_account.pubkey()).unwrap();
+    let vote_state: &VoteState = pod_from_bytes(&vote_account.data).unwrap();
+    let epoch_credits: &EpochCredit = pod_from_bytes(&result.return_data).unwrap();
+
+    assert_eq!(EPOCH, epoch_credits.epoch());
+    assert!(epoch_credits.credits() > 0);
+    assert_eq!(vote_state.epoch_credits(), epoch_credits);
+}
+
+#[test]
+fn test_set_paused_blocks_votes() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    let pause_ixn =
+        instruction::set_paused(vote_account.pubkey(), authorized_withdrawer.pubkey(), true);
+    let unpause_ixn =
+        instruction::set_paused(vote_account.pubkey(), authorized_withdrawer.pubkey(), false);
+    let finalize_ixn = instruction::finalize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        &FinalizationVote::new(SLOT - 1),
+    );
+    let accounts = [
+        (node_key.pubkey(), Account::default()),
+        (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        (authorized_withdrawer.pubkey(), Account::default()),
+        (authorized_voter.pubkey(), Account::default()),
+    ];
+
+    // Votes are rejected while paused
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn.clone(),
+            pause_ixn.clone(),
+            finalize_ixn.clone(),
+        ],
+        &accounts,
+    );
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(VoteError::VotingPaused as u32))
+    );
+
+    // Unpausing restores voting
+    let result = mollusk.process_instruction_chain(
+        &[
+            initialize_ixn.clone(),
+            pause_ixn.clone(),
+            unpause_ixn,
+            finalize_ixn,
+        ],
+        &accounts,
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+
+    assert!(!vote_state.paused());
+    assert_eq!(1, vote_state.finalize_count());
+    assert_eq!(SLOT - 1, vote_state.last_finalized_slot());
+    assert!(vote_state.matches_last_vote(&Vote::new_finalization_vote(SLOT - 1)));
+
+    // The voter cannot pause voting
+    let pause_ixn = instruction::set_paused(vote_account.pubkey(), authorized_voter.pubkey(), true);
+
+    let result = mollusk.process_instruction_chain(&[initialize_ixn, pause_ixn], &accounts);
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_update_validator_identity_basic() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let old_node = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // This (probably) won't fail (p is very low - if it fails, you probably win something)
+    let new_node = Keypair::new();
+    assert_ne!(old_node, new_node);
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &old_node,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    let result = mollusk.process_instruction(
+        &initialize_ixn,
+        &[
+            (old_node.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+
+    assert_eq!(old_node.pubkey(), *vote_state.node_pubkey());
+
+    // Issue an UpdateValidatorIdentity transaction
+    let update_vi_txn = instruction::update_validator_identity(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        new_node.pubkey(),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, update_vi_txn],
+        &[
+            (old_node.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_withdrawer.pubkey(), Account::default()),
+            (new_node.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+
+    assert_eq!(new_node.pubkey(), *vote_state.node_pubkey());
+}
+
+#[test]
+fn test_update_config_commission_and_bls_pubkey() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_pubkey = BlsKeypair::new().public.into();
+    let new_bls_pubkey = BlsKeypair::new().public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Issue an UpdateConfig transaction setting the commission and BLS key
+    let update_config_ixn = instruction::update_config(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        Some(7),
+        None,
+        Some(new_bls_pubkey),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, update_config_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+
+    assert_eq!(7, vote_state.commission());
+    assert_eq!(new_bls_pubkey, *vote_state.bls_pubkey());
+    assert_eq!(node_key.pubkey(), *vote_state.node_pubkey());
+}
+
+#[test]
+fn test_update_config_commission_and_node_pubkey() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let old_node = Keypair::new();
+    let new_node = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let bls_pubkey = BlsKeypair::new().public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &old_node,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Issue an UpdateConfig transaction setting the commission and node identity
+    let update_config_ixn = instruction::update_config(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        Some(50),
+        Some(new_node.pubkey()),
+        None,
+    );
+    let accounts = [
+        (old_node.pubkey(), Account::default()),
+        (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+        (authorized_withdrawer.pubkey(), Account::default()),
+        (new_node.pubkey(), Account::default()),
+    ];
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn.clone(), update_config_ixn.clone()],
+        &accounts,
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+
+    assert_eq!(50, vote_state.commission());
+    assert_eq!(new_node.pubkey(), *vote_state.node_pubkey());
+    assert_eq!(bls_pubkey, *vote_state.bls_pubkey());
+
+    // The new node identity must sign
+    let mut unsigned_ixn = update_config_ixn;
+    unsigned_ixn.accounts[2].is_signer = false;
+
+    let result = mollusk.process_instruction_chain(&[initialize_ixn, unsigned_ixn], &accounts);
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::Custom(
+            VoteError::NodeIdentityMustSign as u32
+        ))
+    );
+}
+
+#[test]
+fn test_withdraw_basic() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let recipient_account = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    let result = mollusk.process_instruction(
+        &initialize_ixn,
+        &[
+            (node_key.pubkey(), Account::default()),
+            (
+                vote_account.pubkey(),
+                build_empty_vote_account_with_excess_lamports(&mollusk, 1_234_567),
+            ),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let account = result.get_account(&vote_account.pubkey()).unwrap();
+
+    let rent_exempt_amount = mollusk.sysvars.rent.minimum_balance(VoteState::size());
+    assert_eq!(rent_exempt_amount + 1_234_567, account.lamports);
+
+    // Issue a Withdraw transaction
+    let withdraw_ixn = instruction::withdraw(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        1_234_567,
+        recipient_account.pubkey(),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, withdraw_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (
+                vote_account.pubkey(),
+                build_empty_vote_account_with_excess_lamports(&mollusk, 1_234_567),
+            ),
+            (authorized_withdrawer.pubkey(), Account::default()),
+            (recipient_account.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    // Ensure that the vote account has the right balance
+    let vote_account = result.get_account(&vote_account.pubkey()).unwrap();
+    assert_eq!(rent_exempt_amount, vote_account.lamports);
+
+    // Ensure that the recipient account has the right balance
+    let recipient_account = result.get_account(&recipient_account.pubkey()).unwrap();
+    assert_eq!(1_234_567, recipient_account.lamports);
+}
+
+#[test]
+fn test_vote_and_withdraw_over_allocated_account() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let recipient_account = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create an over-allocated vote account with lamports to spare
+    let over_allocated_size = VoteState::size() + 1_000;
+    let rent_exempt_amount = mollusk.sysvars.rent.minimum_balance(over_allocated_size);
+    let over_allocated_account = Account::new(
+        rent_exempt_amount + 1_234_567,
+        over_allocated_size,
+        &alpenglow_vote::id(),
+    );
+
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+    let finalize_ixn = instruction::finalize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        &FinalizationVote::new(SLOT - 1),
+    );
+    let withdraw_ixn = instruction::withdraw(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        1_234_567,
+        recipient_account.pubkey(),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, finalize_ixn, withdraw_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), over_allocated_account),
+            (authorized_voter.pubkey(), Account::default()),
+            (authorized_withdrawer.pubkey(), Account::default()),
+            (recipient_account.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    // The vote state lives in the prefix and the excess data is untouched
+    let account = result.get_account(&vote_account.pubkey()).unwrap();
+    assert_eq!(over_allocated_size, account.data.len());
+    assert_eq!(rent_exempt_amount, account.lamports);
+    let vote_state: &VoteState = pod_from_bytes(&account.data[..VoteState::size()]).unwrap();
+    assert_eq!(EPOCH, vote_state.epoch_credits().epoch());
+    assert!(vote_state.epoch_credits().credits() > 0);
+    assert!(account.data[VoteState::size()..]
+        .iter()
+        .all(|byte| *byte == 0));
+
+    assert_eq!(
+        1_234_567,
+        result
+            .get_account(&recipient_account.pubkey())
+            .unwrap()
+            .lamports
+    );
+}
+
+#[test]
+fn test_withdraw_zero_lamports() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let recipient_account = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Issue a zero lamport Withdraw transaction
+    let withdraw_ixn = instruction::withdraw(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        0,
+        recipient_account.pubkey(),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, withdraw_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (
+                vote_account.pubkey(),
+                build_empty_vote_account_with_excess_lamports(&mollusk, 1_234_567),
+            ),
+            (authorized_withdrawer.pubkey(), Account::default()),
+            (recipient_account.pubkey(), Account::default()),
+        ],
+    );
+
+    assert_eq!(Err(InstructionError::InvalidArgument), result.raw_result);
+}
+
+#[test]
+fn test_withdraw_with_memo() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let recipient_account = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create a vote account
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Withdraw with a memo attached
+    let memo = [42; 32];
+    let withdraw_ixn = instruction::withdraw_with_memo(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        1_234,
+        recipient_account.pubkey(),
+        memo,
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, withdraw_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
             (
                 vote_account.pubkey(),
                 build_empty_vote_account_with_excess_lamports(&mollusk, 1_234_567),
@@ -891,12 +1956,283 @@ fn test_withdraw_basic() {
     );
 
     assert!(result.raw_result.is_ok());
+    assert_eq!(memo.to_vec(), result.return_data);
+    assert_eq!(
+        1_234,
+        result
+            .get_account(&recipient_account.pubkey())
+            .unwrap()
+            .lamports
+    );
+}
 
-    // Ensure that the vote account has the right balance
-    let vote_account = result.get_account(&vote_account.pubkey()).unwrap();
-    assert_eq!(rent_exempt_amount, vote_account.lamports);
+#[test]
+fn test_withdraw_previous_version_account() {
+    let mollusk = build_mollusk_with_clock(None);
 
-    // Ensure that the recipient account has the right balance
-    let recipient_account = result.get_account(&recipient_account.pubkey()).unwrap();
-    assert_eq!(1_234_567, recipient_account.lamports);
+    let vote_account = Pubkey::new_unique();
+    let authorized_withdrawer = Keypair::new();
+    let recipient_account = Pubkey::new_unique();
+    let vote_state = VoteState::new_for_tests(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        0,
+        authorized_withdrawer.pubkey(),
+        42,
+        BlsPubkey::default(),
+    );
+
+    // Too few lamports to migrate to the current layout
+    let account = build_previous_version_vote_account(&vote_state, 0);
+    let rent_exempt_amount = mollusk.sysvars.rent.minimum_balance(account.data.len());
+    let account = Account {
+        lamports: rent_exempt_amount + 1_000,
+        ..account
+    };
+
+    // A partial withdrawal leaves the older layout in place
+    let result = mollusk.process_instruction(
+        &instruction::withdraw(
+            vote_account,
+            authorized_withdrawer.pubkey(),
+            1_000,
+            recipient_account,
+        ),
+        &[
+            (vote_account, account.clone()),
+            (recipient_account, Account::default()),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+    assert!(result.raw_result.is_ok());
+    let withdrawn = result.get_account(&vote_account).unwrap();
+    assert_eq!(rent_exempt_amount, withdrawn.lamports);
+    assert_eq!(account.data, withdrawn.data);
+
+    // Emptying the account clears it
+    let result = mollusk.process_instruction(
+        &instruction::withdraw(
+            vote_account,
+            authorized_withdrawer.pubkey(),
+            account.lamports,
+            recipient_account,
+        ),
+        &[
+            (vote_account, account.clone()),
+            (recipient_account, Account::default()),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+    assert!(result.raw_result.is_ok());
+    assert_eq!(
+        account.lamports,
+        result.get_account(&recipient_account).unwrap().lamports
+    );
+    assert!(!VoteState::is_initialized_data(
+        &result.get_account(&vote_account).unwrap().data
+    ));
+}
+
+#[test]
+fn test_migrate_previous_version_account() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Pubkey::new_unique();
+    let authorized_withdrawer = Keypair::new();
+    let vote_state = VoteState::new_for_tests(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        0,
+        authorized_withdrawer.pubkey(),
+        42,
+        BlsPubkey::default(),
+    );
+    let set_paused_ixn =
+        instruction::set_paused(vote_account, authorized_withdrawer.pubkey(), true);
+
+    // The account must be rent exempt at the current size
+    let rent_exempt_amount = mollusk.sysvars.rent.minimum_balance(VoteState::size());
+    let result = mollusk.process_instruction(
+        &set_paused_ixn,
+        &[
+            (
+                vote_account,
+                build_previous_version_vote_account(&vote_state, rent_exempt_amount - 1),
+            ),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+    assert_eq!(result.raw_result, Err(InstructionError::InsufficientFunds));
+
+    // Writers grow the account and rewrite it in the current layout
+    let result = mollusk.process_instruction(
+        &set_paused_ixn,
+        &[
+            (
+                vote_account,
+                build_previous_version_vote_account(&vote_state, rent_exempt_amount),
+            ),
+            (authorized_withdrawer.pubkey(), Account::default()),
+        ],
+    );
+    assert!(result.raw_result.is_ok());
+    let account = result.get_account(&vote_account).unwrap();
+    assert_eq!(VoteState::size(), account.data.len());
+    assert!(!VoteState::needs_migration(&account.data));
+    let migrated = VoteState::deserialize(&account.data).unwrap();
+    assert!(migrated.paused());
+    assert_eq!(42, migrated.commission());
+    assert_eq!(
+        authorized_withdrawer.pubkey(),
+        *migrated.authorized_withdrawer()
+    );
+}
+
+#[test]
+fn test_realloc_refunds_excess_rent() {
+    let mollusk = build_mollusk_with_clock(None);
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let authorized_withdrawer = Keypair::new();
+    let recipient_account = Keypair::new();
+    let bls_keypair = BlsKeypair::new();
+    let bls_pubkey = bls_keypair.public.into();
+
+    // Create an over-allocated vote account
+    let over_allocated_size = VoteState::size() + 1_000;
+    let over_allocated_lamports = mollusk.sysvars.rent.minimum_balance(over_allocated_size);
+    let over_allocated_account = Account::new(
+        over_allocated_lamports,
+        over_allocated_size,
+        &alpenglow_vote::id(),
+    );
+
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &authorized_withdrawer.pubkey(),
+        42,
+        &bls_pubkey,
+    );
+
+    // Shrink it back down to the vote state size
+    let realloc_ixn = instruction::realloc(
+        vote_account.pubkey(),
+        authorized_withdrawer.pubkey(),
+        recipient_account.pubkey(),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn.clone(), realloc_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), over_allocated_account.clone()),
+            (authorized_withdrawer.pubkey(), Account::default()),
+            (recipient_account.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let account = result.get_account(&vote_account.pubkey()).unwrap();
+    let rent_exempt_amount = mollusk.sysvars.rent.minimum_balance(VoteState::size());
+    assert_eq!(VoteState::size(), account.data.len());
+    assert_eq!(rent_exempt_amount, account.lamports);
+    assert_eq!(
+        over_allocated_lamports - rent_exempt_amount,
+        result
+            .get_account(&recipient_account.pubkey())
+            .unwrap()
+            .lamports
+    );
+
+    let vote_state: &VoteState = pod_from_bytes(&account.data).unwrap();
+    assert_eq!(
+        authorized_withdrawer.pubkey(),
+        *vote_state.authorized_withdrawer()
+    );
+
+    // The voter cannot reallocate
+    let realloc_ixn = instruction::realloc(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        recipient_account.pubkey(),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, realloc_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), over_allocated_account),
+            (authorized_voter.pubkey(), Account::default()),
+            (recipient_account.pubkey(), Account::default()),
+        ],
+    );
+
+    assert_eq!(
+        result.raw_result,
+        Err(InstructionError::MissingRequiredSignature)
+    );
+}
+
+#[cfg(feature = "events")]
+#[test]
+fn test_credits_awarded_event() {
+    let mut mollusk = build_mollusk_with_clock(None);
+    let logger = solana_log_collector::LogCollector::new_ref();
+    mollusk.logger = Some(logger.clone());
+
+    let vote_account = Keypair::new();
+    let node_key = Keypair::new();
+    let authorized_voter = Keypair::new();
+    let bls_pubkey = BlsKeypair::new().public.into();
+
+    let initialize_ixn = initialize_vote_account_mollusk(
+        &vote_account,
+        &node_key,
+        &authorized_voter.pubkey(),
+        &Pubkey::new_unique(),
+        42,
+        &bls_pubkey,
+    );
+    let finalize_ixn = instruction::finalize(
+        vote_account.pubkey(),
+        authorized_voter.pubkey(),
+        &FinalizationVote::new(SLOT - 5),
+    );
+
+    let result = mollusk.process_instruction_chain(
+        &[initialize_ixn, finalize_ixn],
+        &[
+            (node_key.pubkey(), Account::default()),
+            (vote_account.pubkey(), build_empty_vote_account(&mollusk)),
+            (authorized_voter.pubkey(), Account::default()),
+        ],
+    );
+
+    assert!(result.raw_result.is_ok());
+
+    let vote_state: &VoteState =
+        pod_from_bytes(&result.get_account(&vote_account.pubkey()).unwrap().data).unwrap();
+    let credits = vote_state.epoch_credits().credits();
+    assert_eq!(13, credits);
+
+    let event = format!(
+        "Program log: event:credits_awarded slot={} epoch={EPOCH} credits={credits}",
+        SLOT - 5
+    );
+    let logger = logger.borrow();
+    assert_eq!(
+        1,
+        logger
+            .get_recorded_content()
+            .iter()
+            .filter(|line| **line == event)
+            .count(),
+        "{:?}",
+        logger.get_recorded_content()
+    );
 }
@@ -0,0 +1,56 @@
+//! `peek_vote_slot` must not allocate. The counting allocator is installed
+//! for this test binary only, so it does not affect the other tests
+
+use {
+    alpenglow_vote::{instruction, vote::Vote},
+    solana_program::{hash::Hash, pubkey::Pubkey},
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    },
+};
+
+/// Counts the allocations made by the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn test_peek_vote_slot_does_not_allocate() {
+    let vote_account = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let votes = [
+        Vote::new_notarization_vote(3, Hash::new_unique(), Hash::new_unique()),
+        Vote::new_finalization_vote(4),
+        Vote::new_skip_vote(5),
+        Vote::new_notarization_fallback_vote(6, Hash::new_unique(), Hash::new_unique()),
+        Vote::new_skip_fallback_vote(7),
+    ];
+    for (vote, slot) in votes.iter().zip(3..) {
+        let ix = vote.to_vote_instruction(vote_account, authority);
+        let before = allocations();
+        let peeked = instruction::peek_vote_slot(&ix.data);
+        assert_eq!(before, allocations());
+        assert_eq!(Ok(Some(slot)), peeked);
+    }
+}